@@ -0,0 +1,27 @@
+#version 330 core
+
+#include "frame_data.glsl"
+
+out vec4 Color;
+
+// xy repeat across greedy quads, z is the block texture layer
+in vec3 TexCoord;
+in vec3 Normal;
+in float CameraDistance;
+in float SkyLight;
+in float BlockLight;
+in float Occlusion;
+
+uniform sampler2DArray blockTextures;
+
+void main()
+{
+    vec4 colour = texture(blockTextures, TexCoord);
+    // Lambert diffuse from the sun on top of a flat ambient term, see game::sun::Sun::light
+    // only reaches faces the sky light does, block light adds a warm glow on top
+    vec3 light = SkyLight * (ambient + sunColour * max(dot(Normal, -sunDirection), 0.0))
+        + BlockLight * vec3(1.0, 0.85, 0.6);
+    light *= Occlusion;
+    float fog = clamp((CameraDistance - fogStart) / (fogEnd - fogStart), 0.0, 1.0);
+    Color = vec4(mix(colour.rgb * light, fogColour, fog), colour.a);
+}
@@ -0,0 +1,51 @@
+#version 330 core
+
+#include "frame_data.glsl"
+
+// packed by game::chunk_vertex::ChunkVertex
+// x: position in bits 0..15, 5 bits per axis, then the block face in bits 15..18,
+//    block light in bits 18..22, sky light in bits 22..26 and ambient occlusion in bits 26..28
+// y: texture coordinates in bits 0..10, 5 bits each, then the texture layer in bits 10..26
+layout (location = 0) in uvec2 aData;
+
+out vec3 TexCoord;
+out vec3 Normal;
+out float CameraDistance;
+out float SkyLight;
+out float BlockLight;
+out float Occlusion;
+
+uniform mat4 model;
+
+// indexed by game::block::BlockFace
+const vec3 FACE_NORMALS[6] = vec3[6](
+    vec3(-1.0, 0.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(0.0, 0.0, -1.0),
+    vec3(1.0, 0.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 0.0, 1.0)
+);
+
+// each level below the brightest is a fifth darker, so light fades out smoothly over its 15 blocks
+float lightCurve(uint level)
+{
+    return pow(0.8, 15.0 - float(level));
+}
+
+// ambient occlusion 0, a corner boxed in on both sides, to 3, an open corner
+const float OCCLUSION_CURVE[4] = float[4](0.45, 0.65, 0.82, 1.0);
+
+void main()
+{
+    vec3 position = vec3(aData.x & 31u, (aData.x >> 5u) & 31u, (aData.x >> 10u) & 31u);
+    vec4 worldPosition = model * vec4(position, 1.0);
+    gl_Position = projection * view * worldPosition;
+    TexCoord = vec3(aData.y & 31u, (aData.y >> 5u) & 31u, (aData.y >> 10u) & 65535u);
+    // chunks are only ever translated, so the normal needs no transforming
+    Normal = FACE_NORMALS[(aData.x >> 15u) & 7u];
+    CameraDistance = distance(worldPosition.xyz, cameraPosition);
+    BlockLight = lightCurve((aData.x >> 18u) & 15u);
+    SkyLight = lightCurve((aData.x >> 22u) & 15u);
+    Occlusion = OCCLUSION_CURVE[(aData.x >> 26u) & 3u];
+}
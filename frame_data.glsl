@@ -0,0 +1,13 @@
+// per-frame data shared by every shader, filled by engine::uniform_buffer::FrameData
+layout (std140) uniform FrameData {
+    mat4 view;
+    mat4 projection;
+    vec3 cameraPosition;
+    float time;
+    vec3 fogColour;
+    float fogStart;
+    float fogEnd;
+    vec3 sunDirection;
+    vec3 sunColour;
+    vec3 ambient;
+};
@@ -1,27 +1,232 @@
-#[allow(dead_code)]
-pub enum BufferType {
-    Vertex,
-    VertexArray,
-    Element,
-    Texture
-}
+use std::mem::size_of_val;
+use std::os::raw::c_void;
+
+use gl::types::*;
 
-#[allow(dead_code)]
+/// Owns a vertex array object, deleting it when dropped.
+///
+/// A default constructed object owns nothing, which lets meshes be built before a GL context exists.
+#[derive(Debug, Default)]
 pub struct VertexArrayObject {
     id: u32
 }
 
-#[allow(dead_code)]
+impl VertexArrayObject {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe { gl::GenVertexArrays(1, &mut id) };
+        Self { id }
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::BindVertexArray(self.id)
+    }
+
+    pub unsafe fn unbind(&self) {
+        gl::BindVertexArray(0)
+    }
+}
+
+impl Drop for VertexArrayObject {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            unsafe { gl::DeleteVertexArrays(1, &self.id) }
+        }
+    }
+}
+
+/// Owns a GL buffer bound to `TARGET`, deleting it when dropped.
+///
+/// Use [`VertexBufferObject`], [`ElementBufferObject`] or [`UniformBufferObject`] rather than naming the target directly.
+#[derive(Debug, Default)]
+pub struct BufferObject<const TARGET: GLenum> {
+    id: u32,
+    /// size in bytes of the last full upload
+    size: usize
+}
+
+pub type VertexBufferObject = BufferObject<{ gl::ARRAY_BUFFER }>;
+pub type ElementBufferObject = BufferObject<{ gl::ELEMENT_ARRAY_BUFFER }>;
+pub type UniformBufferObject = BufferObject<{ gl::UNIFORM_BUFFER }>;
+
+impl<const TARGET: GLenum> BufferObject<TARGET> {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe { gl::GenBuffers(1, &mut id) };
+        Self { id, size: 0 }
+    }
+
+    /// Size in bytes of the buffer's data store
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::BindBuffer(TARGET, self.id)
+    }
+
+    /// Attaches the buffer to an indexed binding point, only valid for targets such as `gl::UNIFORM_BUFFER`
+    pub unsafe fn bind_base(&self, index: GLuint) {
+        gl::BindBufferBase(TARGET, index, self.id)
+    }
+
+    /// Note that unbinding an element buffer while a vertex array is bound detaches it from that vertex array
+    pub unsafe fn unbind(&self) {
+        gl::BindBuffer(TARGET, 0)
+    }
+
+    /// Binds the buffer and replaces its whole data store with `data`
+    pub unsafe fn buffer_data<T>(&mut self, data: &[T], usage: GLenum) {
+        let size = size_of_val(data);
+        self.bind();
+        gl::BufferData(TARGET, size as GLsizeiptr, data.as_ptr() as *const c_void, usage);
+        self.size = size;
+    }
+
+    /// Binds the buffer and overwrites part of its data store, starting `offset` elements of `T` in
+    ///
+    /// Panics if the range falls outside the data store allocated by [`BufferObject::buffer_data`]
+    pub unsafe fn buffer_sub_data<T>(&self, offset: usize, data: &[T]) {
+        let byte_offset = offset * std::mem::size_of::<T>();
+        let size = size_of_val(data);
+        assert!(
+            byte_offset + size <= self.size,
+            "Buffer sub data range {}..{} is outside of the {} byte buffer",
+            byte_offset, byte_offset + size, self.size
+        );
+        self.bind();
+        gl::BufferSubData(TARGET, byte_offset as GLintptr, size as GLsizeiptr, data.as_ptr() as *const c_void);
+    }
+}
+
+impl<const TARGET: GLenum> Drop for BufferObject<TARGET> {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            unsafe { gl::DeleteBuffers(1, &self.id) }
+        }
+    }
+}
+
+/// Owns a texture object, deleting it when dropped
+#[derive(Debug)]
 pub struct TextureBufferObject {
-    id: u32
+    id: u32,
+    target: GLenum,
+    width: i32,
+    height: i32
 }
 
-#[allow(dead_code)]
-pub struct ElementBufferObject {
-    id: u32
+impl Default for TextureBufferObject {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            target: gl::TEXTURE_2D,
+            width: 0,
+            height: 0
+        }
+    }
 }
 
-#[allow(dead_code)]
-pub struct VertexBufferObject {
-    id: u32
-}
\ No newline at end of file
+impl TextureBufferObject {
+    pub fn new(target: GLenum) -> Self {
+        let mut id = 0;
+        unsafe { gl::GenTextures(1, &mut id) };
+        Self { id, target, ..Default::default() }
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::BindTexture(self.target, self.id)
+    }
+
+    /// Binds the texture to texture unit `unit`, leaving that unit active
+    pub unsafe fn bind_to_unit(&self, unit: u32) {
+        gl::ActiveTexture(gl::TEXTURE0 + unit);
+        self.bind()
+    }
+
+    pub unsafe fn unbind(&self) {
+        gl::BindTexture(self.target, 0)
+    }
+
+    pub unsafe fn set_parameter(&self, name: GLenum, value: GLenum) {
+        self.bind();
+        gl::TexParameteri(self.target, name, value as GLint)
+    }
+
+    /// Binds the texture and replaces level 0 with RGBA8 `data`
+    pub unsafe fn image_2d(&mut self, width: i32, height: i32, data: &[u8]) {
+        assert_eq!(data.len(), (width * height * 4) as usize, "Texture data does not match its {}x{} size", width, height);
+        self.bind();
+        gl::TexImage2D(
+            self.target,
+            0,
+            gl::RGBA as GLint,
+            width,
+            height,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_ptr() as *const c_void
+        );
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Binds the texture and replaces level 0 with `depth` RGBA8 images laid out one after another in `data`
+    pub unsafe fn image_3d(&mut self, width: i32, height: i32, depth: i32, data: &[u8]) {
+        assert_eq!(data.len(), (width * height * depth * 4) as usize, "Texture data does not match its {}x{}x{} size", width, height, depth);
+        self.bind();
+        gl::TexImage3D(
+            self.target,
+            0,
+            gl::RGBA as GLint,
+            width,
+            height,
+            depth,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_ptr() as *const c_void
+        );
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Binds the texture and overwrites a region of level 0 with RGBA8 `data`
+    ///
+    /// Panics if the region falls outside of the image allocated by [`TextureBufferObject::image_2d`]
+    #[allow(dead_code)]
+    pub unsafe fn sub_image_2d(&self, x: i32, y: i32, width: i32, height: i32, data: &[u8]) {
+        assert!(
+            x >= 0 && y >= 0 && x + width <= self.width && y + height <= self.height,
+            "Texture region {}x{} at ({}, {}) is outside of the {}x{} texture",
+            width, height, x, y, self.width, self.height
+        );
+        assert_eq!(data.len(), (width * height * 4) as usize, "Texture data does not match its {}x{} region", width, height);
+        self.bind();
+        gl::TexSubImage2D(
+            self.target,
+            0,
+            x,
+            y,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_ptr() as *const c_void
+        );
+    }
+
+    pub unsafe fn generate_mipmap(&self) {
+        self.bind();
+        gl::GenerateMipmap(self.target)
+    }
+}
+
+impl Drop for TextureBufferObject {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            unsafe { gl::DeleteTextures(1, &self.id) }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, Vector4};
+
+/// An axis aligned bounding box in world space
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self { min, max }
+    }
+}
+
+/// The six planes bounding what a camera can see, each stored as `(normal, distance)` with the normal pointing inwards
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Frustum {
+    /// left, right, bottom, top, near, far
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a projection matrix multiplied by a view matrix,
+    /// such as `projection * camera.get_view_matrix()`
+    pub fn from_matrix(view_projection: Matrix4<f32>) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| plane / plane.truncate().magnitude());
+        Self { planes }
+    }
+
+    /// Whether any part of `aabb` may be visible.
+    ///
+    /// Boxes near a corner of the frustum can be reported as visible while lying just outside it,
+    /// which only costs a wasted draw.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane's normal, if even that is behind the plane the whole box is
+            let corner = Vector4::new(
+                if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+                1.0
+            );
+            plane.dot(corner) >= 0.0
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use super::{keybinds::InputFunctionArguments, camera::CameraMovement};
+use crate::game::{chunk::MeshingMode, chunk_position_of};
 
 pub fn set_window_should_close(args: InputFunctionArguments) {
     args.window.unwrap().set_should_close(true)
@@ -48,3 +49,26 @@ pub fn toggle_fullscreen(args: InputFunctionArguments) {
         args.window.unwrap().toggle_fullscreen()
     }
 }
+
+fn other_meshing_mode(mode: MeshingMode) -> MeshingMode {
+    match mode {
+        MeshingMode::Greedy => MeshingMode::Simple,
+        MeshingMode::Simple => MeshingMode::Greedy
+    }
+}
+
+/// Switches every chunk between greedy and simple meshing
+pub fn toggle_meshing_mode(args: InputFunctionArguments) {
+    let world = args.world.unwrap();
+    world.set_meshing_mode(other_meshing_mode(world.meshing_mode()))
+}
+
+/// Switches the chunk the camera is in between greedy and simple meshing, to compare it against its neighbours
+pub fn toggle_chunk_meshing_mode(args: InputFunctionArguments) {
+    let world = args.world.unwrap();
+    let position = args.camera.unwrap().position;
+    let chunk = chunk_position_of(&glm::vec3(position.x, position.y, position.z));
+    if let Some(mode) = world.get_chunk(&chunk).map(|chunk| chunk.meshing) {
+        world.set_chunk_meshing_mode(&chunk, other_meshing_mode(mode));
+    }
+}
@@ -2,6 +2,7 @@ use glfw::{Key, Action};
 use super::window::Window;
 
 use super::camera::Camera;
+use crate::game::world::World;
 
 pub struct KeyBinding {
     pub key: Key,
@@ -48,7 +49,8 @@ pub struct InputFunctionArguments<'a> {
     pub window: Option<&'a mut Window>,
     pub camera: Option<&'a mut Camera>,
     pub delta_time: Option<&'a f32>,
-    pub action: Option<&'a Action>
+    pub action: Option<&'a Action>,
+    pub world: Option<&'a mut World>
 }
 
 impl<'a> InputFunctionArguments<'a> {
@@ -57,7 +59,8 @@ impl<'a> InputFunctionArguments<'a> {
             window: None,
             camera: None,
             delta_time: None,
-            action: None
+            action: None,
+            world: None
         }
     }
 
@@ -87,5 +90,12 @@ impl<'a> InputFunctionArguments<'a> {
             action: Some(action),
             ..self
         }
-    } 
+    }
+
+    pub fn world(self, world: &'a mut World) -> Self {
+        Self {
+            world: Some(world),
+            ..self
+        }
+    }
 }
\ No newline at end of file
@@ -1,70 +1,316 @@
-use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem::size_of_val;
+#[cfg(test)]
 use std::mem::size_of;
-use std::os::raw::c_void;
 use std::ptr;
 
 use gl;
+#[cfg(test)]
+use thiserror::Error;
 use super::shader::Shader;
-use crate::offset_of;
-use glm::{Vec3, Vec2};
+use super::buffer_object::{VertexArrayObject, VertexBufferObject, ElementBufferObject, TextureBufferObject};
+use super::vertex_layout::{self, VertexLayout};
+use crate::vertex_layout;
+use glm::Vec3;
 
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vertex {
     // position
     pub position: Vec3,
     // normal
     pub normal: Vec3,
-    // texCoords
-    pub texCoords: Vec2,
+    // texCoords, z is the layer of an array texture
+    pub texCoords: Vec3,
     // tangent
     pub tangent: Vec3,
     // bitangent
     pub bitangent: Vec3,
 }
 
+vertex_layout! {
+    Vertex {
+        0 => position: [gl::FLOAT; 3],
+        1 => normal: [gl::FLOAT; 3],
+        2 => texCoords: [gl::FLOAT; 3],
+        3 => tangent: [gl::FLOAT; 3],
+        4 => bitangent: [gl::FLOAT; 3],
+    }
+}
+
 impl Default for Vertex {
     fn default() -> Self {
         Vertex {
             position: Vec3::zeros(),
             normal: Vec3::zeros(),
-            texCoords: Vec2::zeros(),
+            texCoords: Vec3::zeros(),
             tangent: Vec3::zeros(),
             bitangent: Vec3::zeros(),
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Texture {
-    pub id: u32,
+/// A texture a mesh samples, named by the image file it is loaded from when the mesh is uploaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureRef {
     pub type_: String,
     pub path: String,
 }
 
-#[derive(Default)]
-pub struct Mesh {
-    /*  Mesh Data  */
-    pub vertices: Vec<Vertex>,
+/// A texture uploaded along with a mesh, deleted when the mesh is dropped
+#[derive(Debug)]
+pub struct Texture {
+    pub type_: String,
+    pub object: TextureBufferObject,
+}
+
+impl Texture {
+    /// Loads the image `reference` names into a mipmapped 2D texture, must be called on the thread that owns the GL context
+    pub fn load(reference: &TextureRef) -> Result<Self, image::ImageError> {
+        let image = image::open(&reference.path)?.to_rgba8();
+        let mut object = TextureBufferObject::new(gl::TEXTURE_2D);
+        unsafe {
+            object.set_parameter(gl::TEXTURE_WRAP_S, gl::REPEAT);
+            object.set_parameter(gl::TEXTURE_WRAP_T, gl::REPEAT);
+            object.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR);
+            object.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+            object.image_2d(image.width() as i32, image.height() as i32, image.as_raw());
+            object.generate_mipmap();
+            object.unbind();
+        }
+        Ok(Self { type_: reference.type_.clone(), object })
+    }
+}
+
+/// Loads every texture a mesh refers to, skipping any that fail with a warning rather than failing the whole mesh
+fn load_textures(references: &[TextureRef]) -> Vec<Texture> {
+    references.iter().filter_map(|reference| match Texture::load(reference) {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            eprintln!("Warning: unable to load texture {}: {}", reference.path, err);
+            None
+        }
+    }).collect()
+}
+
+/// Serialization is only used by tests so far, so it is left out of the game
+#[cfg(test)]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MeshDataError {
+    #[error("Mesh data ended after {0} bytes, expected more")]
+    UnexpectedEnd(usize),
+    #[error("Texture string at byte {0} is not valid utf-8")]
+    InvalidString(usize)
+}
+
+/// Mesh data that lives purely on the CPU.
+///
+/// Can be built, inspected and serialized without a GL context, then turned into a [`Mesh`] with [`MeshData::upload`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshData<V: VertexLayout = Vertex> {
+    pub vertices: Vec<V>,
     pub indices: Vec<u32>,
-    pub textures: Vec<Texture>,
-    pub VAO: u32,
+    pub textures: Vec<TextureRef>,
+}
 
-    /*  Render data  */
-    VBO: u32,
-    EBO: u32,
+impl<V: VertexLayout> Default for MeshData<V> {
+    fn default() -> Self {
+        Self { vertices: Vec::new(), indices: Vec::new(), textures: Vec::new() }
+    }
 }
 
-impl Mesh {
-    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, textures: Vec<Texture>) -> Mesh {
+impl<V: VertexLayout> MeshData<V> {
+    pub fn new(vertices: Vec<V>, indices: Vec<u32>, textures: Vec<TextureRef>) -> Self {
+        Self { vertices, indices, textures }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    #[cfg(test)]
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Sends the data to the GPU, must be called on the thread that owns the GL context
+    pub fn upload(self) -> Mesh<V> {
+        let MeshData { vertices, indices, textures } = self;
         let mut mesh = Mesh {
-            vertices, indices, textures,
-            VAO: 0, VBO: 0, EBO: 0
+            index_count: indices.len() as i32,
+            textures: load_textures(&textures),
+            vao: VertexArrayObject::new(),
+            vbo: VertexBufferObject::new(),
+            ebo: ElementBufferObject::new(),
+            vertex: PhantomData
         };
 
         // now that we have all the required data, set the vertex buffers and its attribute pointers.
-        unsafe { mesh.setup_mesh() }
+        unsafe { mesh.setup_mesh(&vertices, &indices) }
         mesh
     }
+}
+
+#[cfg(test)]
+impl MeshData {
+
+    /// Serializes into a flat little-endian byte buffer, see [`MeshData::from_bytes`]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + self.vertices.len() * size_of::<Vertex>() + self.indices.len() * 4);
+
+        bytes.extend_from_slice(&(self.vertices.len() as u32).to_le_bytes());
+        for vertex in &self.vertices {
+            let fields = vertex.position.iter()
+                .chain(vertex.normal.iter())
+                .chain(vertex.texCoords.iter())
+                .chain(vertex.tangent.iter())
+                .chain(vertex.bitangent.iter());
+            for value in fields {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&(self.indices.len() as u32).to_le_bytes());
+        for index in &self.indices {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.textures.len() as u32).to_le_bytes());
+        for texture in &self.textures {
+            for string in [&texture.type_, &texture.path] {
+                bytes.extend_from_slice(&(string.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(string.as_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MeshDataError> {
+        let mut reader = ByteReader { bytes, offset: 0 };
+
+        let vertex_count = reader.read_u32()? as usize;
+        let mut vertices = Vec::with_capacity(vertex_count.min(bytes.len()));
+        for _ in 0..vertex_count {
+            vertices.push(Vertex {
+                position: reader.read_vec3()?,
+                normal: reader.read_vec3()?,
+                texCoords: reader.read_vec3()?,
+                tangent: reader.read_vec3()?,
+                bitangent: reader.read_vec3()?,
+            });
+        }
+
+        let index_count = reader.read_u32()? as usize;
+        let mut indices = Vec::with_capacity(index_count.min(bytes.len()));
+        for _ in 0..index_count {
+            indices.push(reader.read_u32()?);
+        }
+
+        let texture_count = reader.read_u32()? as usize;
+        let mut textures = Vec::with_capacity(texture_count.min(bytes.len()));
+        for _ in 0..texture_count {
+            textures.push(TextureRef {
+                type_: reader.read_string()?,
+                path: reader.read_string()?,
+            });
+        }
+
+        Ok(Self { vertices, indices, textures })
+    }
+}
+
+#[cfg(test)]
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize
+}
+
+#[cfg(test)]
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MeshDataError> {
+        let end = self.offset.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or(MeshDataError::UnexpectedEnd(self.bytes.len()))?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, MeshDataError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, MeshDataError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_vec3(&mut self) -> Result<Vec3, MeshDataError> {
+        Ok(Vec3::new(self.read_f32()?, self.read_f32()?, self.read_f32()?))
+    }
+
+    fn read_string(&mut self) -> Result<String, MeshDataError> {
+        let len = self.read_u32()? as usize;
+        let start = self.offset;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| MeshDataError::InvalidString(start))
+    }
+}
+
+/// A mesh whose vertex and index data has been uploaded to the GPU.
+///
+/// Each vertex is fed to the shader as described by its [`VertexLayout`].
+/// The GL objects are deleted when the mesh is dropped.
+pub struct Mesh<V: VertexLayout = Vertex> {
+    pub index_count: i32,
+    pub textures: Vec<Texture>,
+
+    /*  Render data  */
+    vao: VertexArrayObject,
+    vbo: VertexBufferObject,
+    ebo: ElementBufferObject,
+    vertex: PhantomData<V>,
+}
+
+impl<V: VertexLayout> Default for Mesh<V> {
+    fn default() -> Self {
+        Mesh {
+            index_count: 0,
+            textures: Vec::new(),
+            vao: VertexArrayObject::default(),
+            vbo: VertexBufferObject::default(),
+            ebo: ElementBufferObject::default(),
+            vertex: PhantomData
+        }
+    }
+}
+
+impl<V: VertexLayout> Mesh<V> {
+    /// Replaces the mesh with `data`, writing over the existing buffers when it fits in them and reallocating them
+    /// when it doesn't. Must be called on the thread that owns the GL context
+    pub fn update(&mut self, data: MeshData<V>) {
+        let MeshData { vertices, indices, textures } = data;
+        unsafe {
+            if size_of_val(vertices.as_slice()) <= self.vbo.size() && size_of_val(indices.as_slice()) <= self.ebo.size() {
+                self.update_vertices(0, &vertices);
+                self.update_indices(0, &indices);
+            } else {
+                self.setup_mesh(&vertices, &indices);
+            }
+        }
+        self.index_count = indices.len() as i32;
+        self.textures = load_textures(&textures);
+    }
+
+    /// Overwrites vertices in place, starting at vertex `offset`, without reallocating the buffer
+    pub unsafe fn update_vertices(&self, offset: usize, vertices: &[V]) {
+        self.vbo.buffer_sub_data(offset, vertices);
+        self.vbo.unbind();
+    }
+
+    /// Overwrites indices in place, starting at index `offset`, without reallocating the buffer
+    pub unsafe fn update_indices(&self, offset: usize, indices: &[u32]) {
+        // the element buffer binding belongs to the vertex array, so it is bound through its own
+        self.vao.bind();
+        self.ebo.buffer_sub_data(offset, indices);
+        self.vao.unbind();
+    }
 
     /// render the mesh
     pub unsafe fn draw(&self, shader: &Shader) {
@@ -97,58 +343,33 @@ impl Mesh {
                 _ => panic!("unknown texture type")
             };
             // now set the sampler to the correct texture unit
-            let sampler = CString::new(format!("{}{}", name, number)).unwrap();
-            gl::Uniform1i(gl::GetUniformLocation(shader.id, sampler.as_ptr()), i as i32);
+            shader.set_uniform(&format!("{}{}", name, number), i as i32);
             // and finally bind the texture
-            gl::BindTexture(gl::TEXTURE_2D, texture.id);
+            texture.object.bind();
         }
 
         // draw mesh
-        gl::BindVertexArray(self.VAO);
-        gl::DrawElements(gl::TRIANGLES, self.indices.len() as i32, gl::UNSIGNED_INT, ptr::null());
-        gl::BindVertexArray(0);
+        self.vao.bind();
+        gl::DrawElements(gl::TRIANGLES, self.index_count, gl::UNSIGNED_INT, ptr::null());
+        self.vao.unbind();
 
         // always good practice to set everything back to defaults once configured.
         gl::ActiveTexture(gl::TEXTURE0);
     }
 
-    unsafe fn setup_mesh(&mut self) {
-        // create buffers/arrays
-        gl::GenVertexArrays(1, &mut self.VAO);
-        gl::GenBuffers(1, &mut self.VBO);
-        gl::GenBuffers(1, &mut self.EBO);
-
-        gl::BindVertexArray(self.VAO);
+    unsafe fn setup_mesh(&mut self, vertices: &[V], indices: &[u32]) {
+        self.vao.bind();
         // load data into vertex buffers
-        gl::BindBuffer(gl::ARRAY_BUFFER, self.VBO);
         // A great thing about structs with repr(C) is that their memory layout is sequential for all its items.
         // The effect is that we can simply pass a pointer to the struct and it translates perfectly to a glm::vec3/2 array which
         // again translates to 3/2 floats which translates to a byte array.
-        let size = (&self.vertices.len() * size_of::<Vertex>()) as isize;
-
-        gl::BufferData(gl::ARRAY_BUFFER, size, self.vertices.as_ptr().cast(), gl::STATIC_DRAW);
-
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.EBO);
-        let size = (&self.indices.len() * size_of::<u32>()) as isize;
-        gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, size, self.indices.as_ptr().cast(), gl::STATIC_DRAW);
+        self.vbo.buffer_data(vertices, gl::STATIC_DRAW);
+        // the element buffer binding is recorded by the bound vertex array
+        self.ebo.buffer_data(indices, gl::STATIC_DRAW);
         // set the vertex attribute pointers
-        let size = size_of::<Vertex>() as i32;
-        // vertex Positions
-        gl::EnableVertexAttribArray(0);
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, size, offset_of!(Vertex, position) as *const c_void);
-        // vertex normals
-        gl::EnableVertexAttribArray(1);
-        gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, size, offset_of!(Vertex, normal) as *const c_void);
-        // vertex texture coords
-        gl::EnableVertexAttribArray(2);
-        gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, size, offset_of!(Vertex, texCoords) as *const c_void);
-        // vertex tangent
-        gl::EnableVertexAttribArray(3);
-        gl::VertexAttribPointer(3, 3, gl::FLOAT, gl::FALSE, size, offset_of!(Vertex, tangent) as *const c_void);
-        // vertex bitangent
-        gl::EnableVertexAttribArray(4);
-        gl::VertexAttribPointer(4, 3, gl::FLOAT, gl::FALSE, size, offset_of!(Vertex, bitangent) as *const c_void);
-
-        gl::BindVertexArray(0);
+        vertex_layout::enable_attributes::<V>();
+
+        self.vao.unbind();
+        self.vbo.unbind();
     }
 }
\ No newline at end of file
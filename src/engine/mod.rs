@@ -3,14 +3,13 @@ pub mod window;
 pub mod input_functions;
 pub mod keybinds;
 pub mod camera;
-pub mod generation;
+pub mod frustum;
 pub mod shader;
+pub mod uniform;
+pub mod uniform_buffer;
+pub mod vertex_layout;
+pub mod preprocessor;
 pub mod util;
 pub mod buffer_object;
 pub mod mesh;
-#[macro_export]
-macro_rules! offset_of {
-    ($ty:ty, $field:ident) => {
-        &(*(ptr::null() as *const $ty)).$field as *const _ as usize
-    }
-}
\ No newline at end of file
+pub mod texture_array;
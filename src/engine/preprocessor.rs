@@ -0,0 +1,162 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PreprocessError {
+    #[error("Unable to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{path}:{line}: expected #include \"file\"")]
+    MalformedInclude { path: PathBuf, line: u32 },
+    #[error("Include cycle: {}", chain.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(" -> "))]
+    IncludeCycle { chain: Vec<PathBuf> },
+    #[error("\"{0}\" is not a valid #define name")]
+    InvalidDefine(String),
+}
+
+/// Where a line of preprocessed source was written
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SourceLocation {
+    /// index into [`PreprocessedSource::files`]
+    pub file: usize,
+    /// 1 based line within that file
+    pub line: u32,
+}
+
+/// A shader with its includes expanded and defines injected, ready for `shader_from_source`
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PreprocessedSource {
+    pub code: String,
+    /// every file that was read, the root file first
+    pub files: Vec<PathBuf>,
+    /// origin of each line of `code`, `None` for injected defines
+    lines: Vec<Option<SourceLocation>>,
+}
+
+impl PreprocessedSource {
+    /// The file and line that 1 based `line` of the preprocessed code came from
+    pub fn locate(&self, line: u32) -> Option<(&Path, u32)> {
+        let location = (*self.lines.get((line as usize).checked_sub(1)?)?)?;
+        Some((&self.files[location.file], location.line))
+    }
+}
+
+/// Reads `path` from disk and preprocesses it, see [`preprocess`].
+///
+/// Every file it tries to read is added to `read`, including when preprocessing fails part way through.
+pub fn preprocess_file<K: AsRef<str>, V: AsRef<str>>(
+    path: &Path,
+    defines: &[(K, V)],
+    read: &mut Vec<PathBuf>
+) -> Result<PreprocessedSource, PreprocessError> {
+    preprocess(path, defines, |path| {
+        if !read.iter().any(|file| file == path) {
+            read.push(path.to_path_buf());
+        }
+        std::fs::read_to_string(path)
+    })
+}
+
+/// Expands `#include "file"` directives, resolving them relative to the including file,
+/// and injects a `#define` for each of `defines` straight after the root file's `#version`.
+///
+/// Files are read through `load` so this can run without touching the disk. `#version` lines in
+/// included files are dropped, and an include that leads back to a file already being included is an error.
+pub fn preprocess<K: AsRef<str>, V: AsRef<str>>(
+    path: &Path,
+    defines: &[(K, V)],
+    mut load: impl FnMut(&Path) -> io::Result<String>
+) -> Result<PreprocessedSource, PreprocessError> {
+    for (name, _) in defines {
+        let name = name.as_ref();
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(PreprocessError::InvalidDefine(String::from(name)));
+        }
+    }
+
+    let mut body = Expansion::default();
+    body.include(&normalize(path), &mut Vec::new(), &mut load)?;
+
+    let mut lines: Vec<(String, Option<SourceLocation>)> = Vec::with_capacity(body.lines.len() + defines.len() + 1);
+    lines.extend(body.version.take());
+    lines.extend(defines.iter().map(|(name, value)| (format!("#define {} {}", name.as_ref(), value.as_ref()), None)));
+    lines.append(&mut body.lines);
+
+    let mut code = String::new();
+    for (line, _) in &lines {
+        code.push_str(line);
+        code.push('\n');
+    }
+    Ok(PreprocessedSource {
+        code,
+        files: body.files,
+        lines: lines.into_iter().map(|(_, location)| location).collect(),
+    })
+}
+
+#[derive(Default)]
+struct Expansion {
+    files: Vec<PathBuf>,
+    /// the root file's `#version` line, which has to stay first
+    version: Option<(String, Option<SourceLocation>)>,
+    lines: Vec<(String, Option<SourceLocation>)>,
+}
+
+impl Expansion {
+    fn include(&mut self, path: &Path, stack: &mut Vec<PathBuf>, load: &mut impl FnMut(&Path) -> io::Result<String>) -> Result<(), PreprocessError> {
+        if stack.iter().any(|including| including == path) {
+            let mut chain = stack.clone();
+            chain.push(path.to_path_buf());
+            return Err(PreprocessError::IncludeCycle { chain });
+        }
+        let source = load(path).map_err(|source| PreprocessError::Io { path: path.to_path_buf(), source })?;
+        let file = match self.files.iter().position(|file| file == path) {
+            Some(file) => file,
+            None => {
+                self.files.push(path.to_path_buf());
+                self.files.len() - 1
+            }
+        };
+        let is_root = stack.is_empty();
+
+        stack.push(path.to_path_buf());
+        for (index, line) in source.lines().enumerate() {
+            let location = SourceLocation { file, line: index as u32 + 1 };
+            let directive = line.trim_start();
+            if let Some(rest) = directive.strip_prefix("#include") {
+                let name = rest.trim().strip_prefix('"').and_then(|rest| rest.strip_suffix('"'))
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| PreprocessError::MalformedInclude { path: path.to_path_buf(), line: location.line })?;
+                let included = normalize(&path.parent().unwrap_or(Path::new("")).join(name));
+                self.include(&included, stack, load)?;
+            } else if directive.starts_with("#version") {
+                if is_root && self.version.is_none() {
+                    self.version = Some((String::from(line), Some(location)));
+                }
+            } else {
+                self.lines.push((String::from(line), Some(location)));
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+}
+
+/// Removes `.` and folds `..` into the preceding directory so the same file always has the same path
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
@@ -1,109 +1,365 @@
-use cgmath::{Vector3, Array, Matrix4, Matrix};
 use gl::{types::*, VERTEX_SHADER, FRAGMENT_SHADER};
-use std::{ffi::{CStr, CString}, fs::read_to_string};
+use std::{cell::RefCell, collections::HashSet, ffi::{CStr, CString}, fmt, fs::metadata, path::{Path, PathBuf}, time::SystemTime};
+use thiserror::Error;
 
+use super::preprocessor::{preprocess_file, PreprocessError, PreprocessedSource};
+use super::uniform::{Uniform, UniformCache, UniformError};
+use super::uniform_buffer::{FRAME_DATA_BINDING, FRAME_DATA_BLOCK};
+use super::vertex_layout::{active_attributes, validate_layout, AttributeInfo, LayoutError, VertexLayout};
 use super::util::create_whitespace_cstring_with_len;
 
-pub struct Shader {
-    pub id: u32
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
 }
 
-impl Shader {
-    pub fn new(vertex_path: &str, fragment_path: &str ) -> Self {
-        let mut shader = Self {id: 0};
+impl ShaderStage {
+    pub fn gl_type(self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => VERTEX_SHADER,
+            ShaderStage::Fragment => FRAGMENT_SHADER,
+        }
+    }
+}
 
-        let vertex_string = read_to_string(vertex_path).unwrap_or_else(|_| panic!("Failed to read vertex shader file: {}", vertex_path));
-        let fragment_string = read_to_string(fragment_path).unwrap_or_else(|_| panic!("Failed to read fragment shader file: {}", fragment_path));
-        
-        let v_shader_code = CString::new(vertex_string.as_bytes()).unwrap();
-        let f_shader_code = CString::new(fragment_string.as_bytes()).unwrap();
-        
-        let vertex_shader = vert_shader_from_source(&v_shader_code).unwrap();
-        let fragment_shader = frag_shader_from_source(&f_shader_code).unwrap();
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::Fragment => "fragment",
+        })
+    }
+}
 
-        unsafe {
-            let id = gl::CreateProgram();
+/// One message from a driver's info log
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ShaderDiagnostic {
+    /// file the message refers to, once the line has been traced back through the preprocessor
+    pub file: Option<PathBuf>,
+    /// source line the message refers to, if the driver gave one
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: {}", file.display(), line, self.message),
+            (None, Some(line)) => write!(f, "line {}: {}", line, self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
 
-            gl::AttachShader(id, vertex_shader);
-            gl::AttachShader(id, fragment_shader);
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("Failed to preprocess {stage} shader: {source}")]
+    Preprocess { stage: ShaderStage, source: PreprocessError },
+    #[error("The {stage} shader {path} contains a nul byte")]
+    InvalidSource { stage: ShaderStage, path: PathBuf },
+    #[error("Failed to compile {stage} shader {path}:{}", DiagnosticList(diagnostics))]
+    Compile { stage: ShaderStage, path: PathBuf, diagnostics: Vec<ShaderDiagnostic> },
+    #[error("Failed to link {vertex_path} with {fragment_path}:{}", DiagnosticList(diagnostics))]
+    Link { vertex_path: PathBuf, fragment_path: PathBuf, diagnostics: Vec<ShaderDiagnostic> },
+}
+
+/// Writes each diagnostic on its own indented line
+struct DiagnosticList<'a>(&'a [ShaderDiagnostic]);
+
+impl fmt::Display for DiagnosticList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in self.0 {
+            write!(f, "\n    {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
 
-            gl::LinkProgram(id);
+/// Splits a driver info log into one diagnostic per line, picking out the source line number.
+///
+/// Understands the common `0:12(5): error: ...` (Mesa), `0(12) : error ...` (NVIDIA)
+/// and `ERROR: 0:12: ...` (AMD) forms; anything else is kept whole without a line.
+pub fn parse_info_log(log: &str) -> Vec<ShaderDiagnostic> {
+    log.lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|line| !line.is_empty())
+        .map(|line| match parse_location(line) {
+            Some((number, message)) => ShaderDiagnostic { file: None, line: Some(number), message },
+            None => ShaderDiagnostic { file: None, line: None, message: String::from(line) },
+        })
+        .collect()
+}
 
-            shader.check_compile_errors(id, "PROGRAM");
+/// Reads the `<string>:<line>` or `<string>(<line>)` location at the start of an info log line,
+/// returning the line number and the message with any `ERROR:`/`WARNING:` tag put back in front
+fn parse_location(line: &str) -> Option<(u32, String)> {
+    let (tag, rest) = ["ERROR:", "WARNING:"]
+        .iter()
+        .find_map(|tag| line.strip_prefix(tag).map(|rest| (Some(tag.trim_end_matches(':').to_lowercase()), rest.trim_start())))
+        .unwrap_or((None, line));
+
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 {
+        return None;
+    }
+    let rest = &rest[digits..];
+    let (number, rest) = if let Some(rest) = rest.strip_prefix(':') {
+        let end = rest.find(|c: char| !c.is_ascii_digit())?;
+        (rest[..end].parse().ok()?, &rest[end..])
+    } else if let Some(rest) = rest.strip_prefix('(') {
+        let (number, rest) = rest.split_once(')')?;
+        (number.parse().ok()?, rest)
+    } else {
+        return None;
+    };
 
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
+    // drop a trailing column and the separator before the message
+    let rest = match rest.strip_prefix('(') {
+        Some(column) => column.split_once(')').map_or(rest, |(_, rest)| rest),
+        None => rest,
+    };
+    let message = rest.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+    Some((number, match tag {
+        Some(tag) => format!("{}: {}", tag, message),
+        None => String::from(message),
+    }))
+}
 
-            shader.id = id;
+/// The source files and defines a shader is built from, and when each file it read was last seen to change
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShaderSources {
+    pub vertex_path: PathBuf,
+    pub fragment_path: PathBuf,
+    /// injected into both stages, see [`preprocess`](super::preprocessor::preprocess)
+    pub defines: Vec<(String, String)>,
+    watched: Vec<(PathBuf, Option<SystemTime>)>,
+}
 
+impl ShaderSources {
+    pub fn new(vertex_path: impl AsRef<Path>, fragment_path: impl AsRef<Path>) -> Self {
+        let mut sources = Self {
+            vertex_path: vertex_path.as_ref().to_path_buf(),
+            fragment_path: fragment_path.as_ref().to_path_buf(),
+            ..Default::default()
         };
-        shader
+        sources.watch([sources.vertex_path.clone(), sources.fragment_path.clone()]);
+        sources
     }
 
-    pub unsafe fn use_program(&self) {
-        gl::UseProgram(self.id)
+    /// Replaces the files polled for changes, such as with every file the last build read
+    pub fn watch(&mut self, files: impl IntoIterator<Item = PathBuf>) {
+        self.watched = files.into_iter().map(|path| {
+            let modified = modified_time(&path);
+            (path, modified)
+        }).collect();
     }
 
-    #[allow(dead_code)]
-    pub unsafe fn set_bool(&self, name: &CStr, value: bool) {
-        gl::Uniform1i(gl::GetUniformLocation(self.id, name.as_ptr()), value as i32)
+    /// Whether any watched file's modification time has changed since the last poll.
+    ///
+    /// Files that can't be read, for instance while an editor is saving them, count as unchanged.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, seen) in self.watched.iter_mut() {
+            let current = modified_time(path);
+            if current.is_some() && current != *seen {
+                *seen = current;
+                changed = true;
+            }
+        }
+        changed
     }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// A linked shader program that can rebuild itself when its source files change.
+///
+/// Active uniforms and vertex inputs are looked up whenever the program links. The program is deleted when the shader is dropped.
+#[derive(Default)]
+pub struct Shader {
+    pub id: u32,
+    sources: ShaderSources,
+    uniforms: UniformCache,
+    attributes: Vec<AttributeInfo>,
+    /// uniform problems already reported, so a bad name set every frame only warns once
+    warned: RefCell<HashSet<UniformError>>,
+}
 
+impl Shader {
     #[allow(dead_code)]
-    pub unsafe fn set_int(&self, name: &CStr, value: i32) {
-        gl::Uniform1i(gl::GetUniformLocation(self.id, name.as_ptr()), value)
+    pub fn new(vertex_path: impl AsRef<Path>, fragment_path: impl AsRef<Path>) -> Result<Self, ShaderError> {
+        let mut shader = Self::unlinked(ShaderSources::new(vertex_path, fragment_path));
+        shader.reload()?;
+        Ok(shader)
     }
 
-    #[allow(dead_code)]
-    pub unsafe fn set_float(&self, name: &CStr, value: f32) {
-        gl::Uniform1f(gl::GetUniformLocation(self.id, name.as_ptr()), value)
+    /// A shader with no program yet that watches its sources, to be built with [`Shader::reload`] so a build that
+    /// fails can carry on watching every file it read. Drawing with it renders nothing until a reload succeeds.
+    pub fn unlinked(sources: ShaderSources) -> Self {
+        Self { id: 0, sources, uniforms: UniformCache::default(), attributes: Vec::new(), warned: RefCell::default() }
     }
 
-    #[allow(dead_code)]
-    pub unsafe fn set_vector3(&self, name: &CStr, value: &Vector3<f32>) {
-        gl::Uniform3fv(gl::GetUniformLocation(self.id, name.as_ptr()), 1, value.as_ptr())
+    pub fn sources(&self) -> &ShaderSources {
+        &self.sources
     }
 
-    #[allow(dead_code)]
-    pub unsafe fn set_vec3(&self, name: &CStr, x: f32, y: f32, z: f32) {
-        gl::Uniform3f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z)
+    /// Rebuilds the program if either source file has changed since the last call.
+    ///
+    /// The new program only replaces the current one if it compiles and links, otherwise the current
+    /// program stays in use and the error is returned. Returns whether the program was replaced.
+    pub fn reload_if_changed(&mut self) -> Result<bool, ShaderError> {
+        if !self.sources.poll() {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
     }
 
-    pub unsafe fn set_vec4(&self, name: &str, x: f32, y: f32, z: f32, w: f32) {
-        let c_name = CString::new(name).unwrap();
-        gl::Uniform4f(gl::GetUniformLocation(self.id, c_name.as_ptr()), x, y, z, w)
+    /// Rebuilds the program, replacing the current one if it compiles and links.
+    ///
+    /// Whether or not it succeeds, the files watched for changes become every file the build read, so fixing
+    /// a broken include is noticed too.
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        // both stages stay watched even if the first to fail stops the other being read
+        let mut files = vec![self.sources.vertex_path.clone(), self.sources.fragment_path.clone()];
+        let built = build_program(&self.sources, &mut files);
+        self.sources.watch(files);
+        let id = built?;
+        if self.id != 0 {
+            unsafe { gl::DeleteProgram(self.id) };
+        }
+        self.id = id;
+        unsafe { bind_uniform_block(id, FRAME_DATA_BLOCK, FRAME_DATA_BINDING) };
+        self.uniforms = unsafe { UniformCache::from_program(id) };
+        self.attributes = unsafe { active_attributes(id) };
+        self.warned.borrow_mut().clear();
+        Ok(())
     }
 
-    pub unsafe fn set_mat4(&self, name: &str, mat: &Matrix4<f32>) {
-        let c_name = CString::new(name).unwrap();
-        gl::UniformMatrix4fv(gl::GetUniformLocation(self.id, c_name.as_ptr()), 1, gl::FALSE, mat.as_ptr())
+    /// The active vertex inputs of the program, sorted by location
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
     }
 
-    unsafe fn check_compile_errors(&self, shader: u32, type_: &str) {
-        let mut success = gl::FALSE as GLint;
-        let mut info_log: Vec<u8> = vec![0; 1024];
-        info_log.set_len(1024 - 1); // subtract 1 to skip the trailing null character
-        if type_ != "PROGRAM" {
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetShaderInfoLog(shader, 1024, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                println!("ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                         type_,
-                         std::str::from_utf8(&info_log).unwrap());
-            }
+    /// Checks that meshes of `V` vertices feed every input the program reads with the right type
+    pub fn check_vertex_layout<V: VertexLayout>(&self) -> Result<(), LayoutError> {
+        validate_layout(self.attributes(), V::ATTRIBUTES)
+    }
 
-        } else {
-            gl::GetProgramiv(shader, gl::LINK_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetProgramInfoLog(shader, 1024, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                println!("ERROR::PROGRAM_LINKING_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                         type_,
-                         std::str::from_utf8(&info_log).unwrap());
+    pub unsafe fn use_program(&self) {
+        gl::UseProgram(self.id)
+    }
+
+    /// Sets a uniform of this program, which must be in use.
+    ///
+    /// Names that aren't active in the program, or whose GLSL type doesn't match `T`, are skipped
+    /// with a warning printed the first time.
+    pub unsafe fn set_uniform<T: Uniform>(&self, name: &str, value: T) {
+        match self.uniforms.location::<T>(name) {
+            Ok(location) => value.set(location),
+            // an unlinked shader has nothing to set
+            Err(_) if self.id == 0 => {},
+            Err(err) => {
+                if self.warned.borrow_mut().insert(err.clone()) {
+                    eprintln!(
+                        "Warning: {} with {}: {}",
+                        self.sources.vertex_path.display(), self.sources.fragment_path.display(), err
+                    );
+                }
             }
         }
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            unsafe { gl::DeleteProgram(self.id) }
+        }
+    }
+}
+
+/// Points the uniform block `name` at a buffer binding point, returning false if the program doesn't declare it
+unsafe fn bind_uniform_block(program: GLuint, name: &str, binding: GLuint) -> bool {
+    let (true, Ok(name)) = (program != 0, CString::new(name)) else {
+        return false;
+    };
+    let index = gl::GetUniformBlockIndex(program, name.as_ptr());
+    if index == gl::INVALID_INDEX {
+        return false;
+    }
+    gl::UniformBlockBinding(program, index, binding);
+    true
+}
+
+/// Compiles both stages and links them into a new program, adding every file that was read to `files`
+/// even if the build fails
+fn build_program(sources: &ShaderSources, files: &mut Vec<PathBuf>) -> Result<GLuint, ShaderError> {
+    let vertex_shader = compile_file(ShaderStage::Vertex, &sources.vertex_path, &sources.defines, files)?;
+    let fragment_shader = match compile_file(ShaderStage::Fragment, &sources.fragment_path, &sources.defines, files) {
+        Ok(shader) => shader,
+        Err(err) => {
+            unsafe { gl::DeleteShader(vertex_shader) };
+            return Err(err);
+        }
+    };
+
+    let linked = link_program(vertex_shader, fragment_shader);
+    unsafe {
+        gl::DeleteShader(vertex_shader);
+        gl::DeleteShader(fragment_shader);
+    }
+    linked.map_err(|log| ShaderError::Link {
+        vertex_path: sources.vertex_path.clone(),
+        fragment_path: sources.fragment_path.clone(),
+        diagnostics: parse_info_log(&log)
+    })
+}
+
+/// Preprocesses and compiles one stage, adding the files it reads to `files`
+fn compile_file(stage: ShaderStage, path: &Path, defines: &[(String, String)], files: &mut Vec<PathBuf>) -> Result<GLuint, ShaderError> {
+    let preprocessed = preprocess_file(path, defines, files).map_err(|source| ShaderError::Preprocess { stage, source })?;
+    let source = CString::new(preprocessed.code.as_str()).map_err(|_| ShaderError::InvalidSource { stage, path: path.to_path_buf() })?;
+    shader_from_source(&source, stage.gl_type()).map_err(|log| ShaderError::Compile {
+        stage,
+        path: path.to_path_buf(),
+        diagnostics: trace_diagnostics(parse_info_log(&log), &preprocessed)
+    })
+}
+
+/// Points diagnostics at the file and line they came from before preprocessing
+pub fn trace_diagnostics(diagnostics: Vec<ShaderDiagnostic>, source: &PreprocessedSource) -> Vec<ShaderDiagnostic> {
+    diagnostics.into_iter().map(|diagnostic| {
+        match diagnostic.line.and_then(|line| source.locate(line)) {
+            Some((file, line)) => ShaderDiagnostic { file: Some(file.to_path_buf()), line: Some(line), ..diagnostic },
+            None => diagnostic,
+        }
+    }).collect()
+}
+
+/// Links the two stages into a new program, returning the info log and deleting the program if linking fails
+fn link_program(vertex_shader: GLuint, fragment_shader: GLuint) -> Result<GLuint, String> {
+    unsafe {
+        let id = gl::CreateProgram();
+        gl::AttachShader(id, vertex_shader);
+        gl::AttachShader(id, fragment_shader);
+        gl::LinkProgram(id);
+
+        let mut success: GLint = 0;
+        gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+        if success == gl::TRUE as GLint {
+            return Ok(id);
+        }
 
+        let mut len: GLint = 0;
+        gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+        let error = create_whitespace_cstring_with_len(len.max(0) as usize);
+        gl::GetProgramInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut GLchar);
+        gl::DeleteProgram(id);
+        Err(error.to_string_lossy().into_owned())
     }
 }
 
@@ -151,6 +407,7 @@ fn shader_from_source(source: &CStr, type_: GLenum) -> Result<GLuint, String> {
                 error.as_ptr() as *mut gl::types::GLchar
             );
         }
+        unsafe { gl::DeleteShader(id) };
         return Err(error.to_string_lossy().into_owned());
     }
     Ok(id)
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use super::buffer_object::TextureBufferObject;
+
+#[derive(Debug, Error)]
+pub enum TextureArrayError {
+    #[error("Unable to load texture {path}: {source}")]
+    Image { path: PathBuf, source: image::ImageError },
+    #[error("Texture {path} is {found:?} but the first layer is {expected:?}, every layer must be the same size")]
+    SizeMismatch { path: PathBuf, expected: (u32, u32), found: (u32, u32) },
+    #[error("A texture array needs at least one layer")]
+    Empty,
+}
+
+/// The RGBA8 pixels of every layer of a texture array, loaded on the CPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureLayers {
+    pub width: u32,
+    pub height: u32,
+    pub layers: u32,
+    /// each layer's pixels one after another, in the order the paths were given
+    pub data: Vec<u8>,
+}
+
+impl TextureLayers {
+    /// Decodes each image into the next layer, they must all share the first one's size
+    pub fn load<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self, TextureArrayError> {
+        let mut layers: Option<Self> = None;
+        for path in paths {
+            let path = path.as_ref();
+            let image = image::open(path)
+                .map_err(|source| TextureArrayError::Image { path: path.to_path_buf(), source })?
+                .to_rgba8();
+            let layers = layers.get_or_insert_with(|| Self {
+                width: image.width(),
+                height: image.height(),
+                layers: 0,
+                data: Vec::new(),
+            });
+            if image.dimensions() != (layers.width, layers.height) {
+                return Err(TextureArrayError::SizeMismatch {
+                    path: path.to_path_buf(),
+                    expected: (layers.width, layers.height),
+                    found: image.dimensions(),
+                });
+            }
+            layers.data.extend_from_slice(image.as_raw());
+            layers.layers += 1;
+        }
+        layers.ok_or(TextureArrayError::Empty)
+    }
+
+    /// Sends the layers to the GPU as a `GL_TEXTURE_2D_ARRAY`, must be called on the thread that owns the GL context
+    pub fn upload(&self) -> TextureBufferObject {
+        let mut texture = TextureBufferObject::new(gl::TEXTURE_2D_ARRAY);
+        unsafe {
+            // repeat so greedy quads can tile a texture across their whole face
+            texture.set_parameter(gl::TEXTURE_WRAP_S, gl::REPEAT);
+            texture.set_parameter(gl::TEXTURE_WRAP_T, gl::REPEAT);
+            texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_LINEAR);
+            texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST);
+            texture.image_3d(self.width as i32, self.height as i32, self.layers as i32, &self.data);
+            texture.generate_mipmap();
+            texture.unbind();
+        }
+        texture
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+use cgmath::{Array, Matrix};
+use gl::types::*;
+use thiserror::Error;
+
+/// A value that can be written to a uniform of one of the GLSL types it [`accepts`](Uniform::accepts)
+pub trait Uniform {
+    /// Whether a uniform of GL type `gl_type`, such as `gl::FLOAT_VEC3`, can be set from this value
+    fn accepts(gl_type: GLenum) -> bool;
+
+    /// Writes the value to `location` of the program currently in use
+    unsafe fn set(&self, location: GLint);
+}
+
+impl<T: Uniform + ?Sized> Uniform for &T {
+    fn accepts(gl_type: GLenum) -> bool {
+        T::accepts(gl_type)
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        (**self).set(location)
+    }
+}
+
+impl Uniform for f32 {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1f(location, *self)
+    }
+}
+
+impl Uniform for i32 {
+    /// Samplers are set to the texture unit they read from
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::INT || gl_type == gl::BOOL || is_sampler(gl_type)
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self)
+    }
+}
+
+impl Uniform for u32 {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::UNSIGNED_INT
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1ui(location, *self)
+    }
+}
+
+impl Uniform for bool {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::BOOL
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self as GLint)
+    }
+}
+
+/// Implements [`Uniform`] for float vector and matrix types that expose a pointer to their column major data
+macro_rules! impl_uniform {
+    ($gl_type:expr, |$location:ident, $pointer:ident| $set:expr, $($ty:ty => $as_ptr:path),+) => {
+        $(
+            impl Uniform for $ty {
+                fn accepts(gl_type: GLenum) -> bool {
+                    gl_type == $gl_type
+                }
+
+                unsafe fn set(&self, $location: GLint) {
+                    let $pointer: *const f32 = $as_ptr(self);
+                    $set
+                }
+            }
+        )+
+    };
+}
+
+impl_uniform!(gl::FLOAT_VEC2, |location, pointer| gl::Uniform2fv(location, 1, pointer),
+    cgmath::Vector2<f32> => Array::as_ptr, glm::Vec2 => glm::Vec2::as_ptr);
+impl_uniform!(gl::FLOAT_VEC3, |location, pointer| gl::Uniform3fv(location, 1, pointer),
+    cgmath::Vector3<f32> => Array::as_ptr, cgmath::Point3<f32> => Array::as_ptr, glm::Vec3 => glm::Vec3::as_ptr);
+impl_uniform!(gl::FLOAT_VEC4, |location, pointer| gl::Uniform4fv(location, 1, pointer),
+    cgmath::Vector4<f32> => Array::as_ptr, glm::Vec4 => glm::Vec4::as_ptr);
+impl_uniform!(gl::FLOAT_MAT3, |location, pointer| gl::UniformMatrix3fv(location, 1, gl::FALSE, pointer),
+    cgmath::Matrix3<f32> => Matrix::as_ptr, glm::Mat3 => glm::Mat3::as_ptr);
+impl_uniform!(gl::FLOAT_MAT4, |location, pointer| gl::UniformMatrix4fv(location, 1, gl::FALSE, pointer),
+    cgmath::Matrix4<f32> => Matrix::as_ptr, glm::Mat4 => glm::Mat4::as_ptr);
+
+fn is_sampler(gl_type: GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_2D_ARRAY_SHADOW | gl::INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::INT_SAMPLER_2D_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+    )
+}
+
+/// The GLSL spelling of a uniform type, for error messages
+pub fn gl_type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        _ if is_sampler(gl_type) => "sampler",
+        _ => "unknown type",
+    }
+}
+
+/// An active uniform of a linked program
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct UniformInfo {
+    pub location: GLint,
+    pub gl_type: GLenum,
+    /// number of elements, more than 1 for arrays
+    pub size: GLint,
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone, Hash)]
+pub enum UniformError {
+    #[error("No active uniform named \"{0}\", it may be misspelt or optimised out")]
+    Missing(String),
+    #[error("Uniform \"{name}\" is a {} but was set with a {value_type}", gl_type_name(*gl_type))]
+    WrongType { name: String, gl_type: GLenum, value_type: &'static str },
+}
+
+/// Locations and types of a program's active uniforms, looked up once after it links
+#[derive(Debug, Clone, Default)]
+pub struct UniformCache {
+    uniforms: HashMap<String, UniformInfo>,
+}
+
+impl UniformCache {
+    /// Queries every active uniform of a linked program
+    pub unsafe fn from_program(program: GLuint) -> Self {
+        let mut cache = Self::default();
+        if program == 0 {
+            return cache;
+        }
+        let mut count: GLint = 0;
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        let mut name_buffer = [0 as GLchar; 256];
+        for index in 0..count.max(0) as GLuint {
+            let (mut length, mut size, mut gl_type) = (0, 0, 0);
+            gl::GetActiveUniform(program, index, name_buffer.len() as GLsizei, &mut length, &mut size, &mut gl_type, name_buffer.as_mut_ptr());
+            let name = CStr::from_ptr(name_buffer.as_ptr()).to_string_lossy().into_owned();
+            let location = gl::GetUniformLocation(program, name_buffer.as_ptr());
+            // uniforms in blocks have no location and are set through their buffer instead
+            if location >= 0 {
+                cache.insert(&name, UniformInfo { location, gl_type, size });
+            }
+        }
+        cache
+    }
+
+    /// Adds a uniform, arrays reported as `name[0]` can also be found as `name`
+    pub fn insert(&mut self, name: &str, info: UniformInfo) {
+        if let Some(array) = name.strip_suffix("[0]") {
+            self.uniforms.insert(String::from(array), info);
+        }
+        self.uniforms.insert(String::from(name), info);
+    }
+
+    /// The location to write a `T` to for uniform `name`, checking that it exists and has a matching type
+    pub fn location<T: Uniform>(&self, name: &str) -> Result<GLint, UniformError> {
+        let info = self.uniforms.get(name).ok_or_else(|| UniformError::Missing(String::from(name)))?;
+        if !T::accepts(info.gl_type) {
+            return Err(UniformError::WrongType {
+                name: String::from(name),
+                gl_type: info.gl_type,
+                value_type: std::any::type_name::<T>(),
+            });
+        }
+        Ok(info.location)
+    }
+}
@@ -0,0 +1,129 @@
+use cgmath::{Matrix4, Point3, Vector3};
+use gl::types::*;
+
+use super::buffer_object::UniformBufferObject;
+
+/// Name of the per-frame block shaders declare by including `frame_data.glsl`
+pub const FRAME_DATA_BLOCK: &str = "FrameData";
+/// Binding point the [`FrameDataBuffer`] is attached to, every shader's `FrameData` block is pointed at it when it links
+pub const FRAME_DATA_BINDING: GLuint = 0;
+
+/// Lays values out by the std140 rules so they can be copied straight into a uniform block.
+///
+/// Values must be written in the order the block declares them.
+#[derive(Debug, Clone, Default)]
+pub struct Std140Writer {
+    bytes: Vec<u8>,
+}
+
+impl Std140Writer {
+    fn align(&mut self, alignment: usize) {
+        let padded = self.bytes.len().div_ceil(alignment) * alignment;
+        self.bytes.resize(padded, 0);
+    }
+
+    fn write(&mut self, alignment: usize, values: &[f32]) {
+        self.align(alignment);
+        for value in values {
+            self.bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    pub fn float(mut self, value: f32) -> Self {
+        self.write(4, &[value]);
+        self
+    }
+
+    /// A `vec3` is aligned like a `vec4`, but a following scalar packs into its fourth component
+    pub fn vec3(mut self, value: [f32; 3]) -> Self {
+        self.write(16, &value);
+        self
+    }
+
+    /// Column major, each column aligned like a `vec4`
+    pub fn mat4(mut self, value: &[f32; 16]) -> Self {
+        self.write(16, value);
+        self
+    }
+
+    /// The written bytes, padded to a multiple of 16 like the block itself
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align(16);
+        self.bytes
+    }
+}
+
+/// Everything shaders share for one frame, mirrors the `FrameData` block in `frame_data.glsl`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameData {
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    pub camera_position: Point3<f32>,
+    /// seconds since the window opened
+    pub time: f32,
+    pub fog_colour: Vector3<f32>,
+    /// distance from the camera fog starts at
+    pub fog_start: f32,
+    /// distance from the camera fog completely hides everything at
+    pub fog_end: f32,
+    /// unit vector the sunlight travels along
+    pub sun_direction: Vector3<f32>,
+    pub sun_colour: Vector3<f32>,
+    pub ambient: Vector3<f32>,
+}
+
+impl Default for FrameData {
+    fn default() -> Self {
+        Self {
+            view: Matrix4::from_scale(1.0),
+            projection: Matrix4::from_scale(1.0),
+            camera_position: Point3::new(0.0, 0.0, 0.0),
+            time: 0.0,
+            fog_colour: Vector3::new(0.0, 0.0, 0.0),
+            fog_start: f32::MAX,
+            fog_end: f32::MAX,
+            sun_direction: Vector3::new(0.0, -1.0, 0.0),
+            sun_colour: Vector3::new(0.0, 0.0, 0.0),
+            ambient: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl FrameData {
+    /// The block's contents in std140 layout
+    pub fn to_std140(self) -> Vec<u8> {
+        Std140Writer::default()
+            .mat4(self.view.as_ref())
+            .mat4(self.projection.as_ref())
+            .vec3(self.camera_position.into())
+            .float(self.time)
+            .vec3(self.fog_colour.into())
+            .float(self.fog_start)
+            .float(self.fog_end)
+            .vec3(self.sun_direction.into())
+            .vec3(self.sun_colour.into())
+            .vec3(self.ambient.into())
+            .finish()
+    }
+}
+
+/// The uniform buffer holding [`FrameData`], attached to [`FRAME_DATA_BINDING`] for as long as it lives
+pub struct FrameDataBuffer {
+    buffer: UniformBufferObject,
+}
+
+impl FrameDataBuffer {
+    pub unsafe fn new() -> Self {
+        let mut buffer = UniformBufferObject::new();
+        buffer.buffer_data(&FrameData::default().to_std140(), gl::DYNAMIC_DRAW);
+        buffer.bind_base(FRAME_DATA_BINDING);
+        buffer.unbind();
+        Self { buffer }
+    }
+
+    /// Uploads this frame's data, once per frame before anything is drawn
+    pub unsafe fn update(&mut self, data: &FrameData) {
+        self.buffer.buffer_sub_data(0, &data.to_std140());
+        self.buffer.unbind();
+    }
+}
@@ -0,0 +1,190 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::mem::size_of;
+use std::os::raw::c_void;
+
+use gl::types::*;
+use thiserror::Error;
+
+use super::uniform::gl_type_name;
+
+/// How the shader sees an attribute's components
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AttributeKind {
+    /// Read as floats, integer components are converted as is
+    Float,
+    /// Read as floats, integer components are mapped to 0..1 (or -1..1 if signed)
+    Normalized,
+    /// Read as `int`/`uint` inputs, which needs an integer component type
+    Integer,
+}
+
+/// One attribute of a vertex type, as a mesh feeds it to the vertex shader
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct VertexAttribute {
+    pub name: &'static str,
+    pub location: u32,
+    /// 1 to 4
+    pub components: i32,
+    /// type of each component, such as `gl::FLOAT`
+    pub component_type: GLenum,
+    pub kind: AttributeKind,
+    /// byte offset of the field within the vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    /// The GLSL input type this attribute should be read as, such as `gl::FLOAT_VEC3` for three floats
+    pub fn shader_type(&self) -> GLenum {
+        let types = match (self.kind, self.component_type) {
+            (AttributeKind::Integer, gl::BYTE | gl::SHORT | gl::INT) => [gl::INT, gl::INT_VEC2, gl::INT_VEC3, gl::INT_VEC4],
+            (AttributeKind::Integer, _) => [gl::UNSIGNED_INT, gl::UNSIGNED_INT_VEC2, gl::UNSIGNED_INT_VEC3, gl::UNSIGNED_INT_VEC4],
+            _ => [gl::FLOAT, gl::FLOAT_VEC2, gl::FLOAT_VEC3, gl::FLOAT_VEC4],
+        };
+        types[(self.components.clamp(1, 4) - 1) as usize]
+    }
+}
+
+/// Describes how a `#[repr(C)]` vertex struct is laid out for the vertex shader.
+///
+/// Implement it with [`vertex_layout!`](crate::vertex_layout) rather than by hand so the offsets always match the struct.
+pub trait VertexLayout: Copy {
+    const ATTRIBUTES: &'static [VertexAttribute];
+}
+
+/// Points every attribute of `V` at the bound vertex buffer, recording them in the bound vertex array
+pub unsafe fn enable_attributes<V: VertexLayout>() {
+    let stride = size_of::<V>() as GLsizei;
+    for attribute in V::ATTRIBUTES {
+        let offset = attribute.offset as *const c_void;
+        gl::EnableVertexAttribArray(attribute.location);
+        match attribute.kind {
+            AttributeKind::Integer => {
+                gl::VertexAttribIPointer(attribute.location, attribute.components, attribute.component_type, stride, offset)
+            },
+            AttributeKind::Float | AttributeKind::Normalized => gl::VertexAttribPointer(
+                attribute.location,
+                attribute.components,
+                attribute.component_type,
+                (attribute.kind == AttributeKind::Normalized) as GLboolean,
+                stride,
+                offset
+            ),
+        }
+    }
+}
+
+/// Implements [`VertexLayout`] for a `#[repr(C)]` struct, listing each attribute as
+/// `location => field: [component_type; components]`, optionally followed by `as Normalized` or `as Integer`.
+///
+/// ```ignore
+/// vertex_layout! {
+///     LineVertex {
+///         0 => position: [gl::FLOAT; 3],
+///         1 => colour: [gl::UNSIGNED_BYTE; 4] as Normalized,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! vertex_layout {
+    ($ty:ty { $($location:literal => $field:ident: [$component_type:expr; $components:literal] $(as $kind:ident)?),+ $(,)? }) => {
+        impl $crate::engine::vertex_layout::VertexLayout for $ty {
+            const ATTRIBUTES: &'static [$crate::engine::vertex_layout::VertexAttribute] = &[
+                $(
+                    $crate::engine::vertex_layout::VertexAttribute {
+                        name: stringify!($field),
+                        location: $location,
+                        components: $components,
+                        component_type: $component_type,
+                        kind: $crate::vertex_layout!(@kind $($kind)?),
+                        offset: std::mem::offset_of!($ty, $field),
+                    },
+                )+
+            ];
+        }
+    };
+    (@kind) => { $crate::engine::vertex_layout::AttributeKind::Float };
+    (@kind $kind:ident) => { $crate::engine::vertex_layout::AttributeKind::$kind };
+}
+
+/// An active input of a linked vertex shader
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AttributeInfo {
+    pub name: String,
+    /// -1 for built in inputs such as `gl_VertexID`
+    pub location: GLint,
+    pub gl_type: GLenum,
+}
+
+/// Queries every active vertex input of a linked program, sorted by location
+pub unsafe fn active_attributes(program: GLuint) -> Vec<AttributeInfo> {
+    if program == 0 {
+        return Vec::new();
+    }
+    let mut count: GLint = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut count);
+    let mut name_buffer = [0 as GLchar; 256];
+    let mut attributes: Vec<AttributeInfo> = (0..count.max(0) as GLuint).map(|index| {
+        let (mut length, mut size, mut gl_type) = (0, 0, 0);
+        gl::GetActiveAttrib(program, index, name_buffer.len() as GLsizei, &mut length, &mut size, &mut gl_type, name_buffer.as_mut_ptr());
+        AttributeInfo {
+            name: CStr::from_ptr(name_buffer.as_ptr()).to_string_lossy().into_owned(),
+            location: gl::GetAttribLocation(program, name_buffer.as_ptr()),
+            gl_type,
+        }
+    }).collect();
+    attributes.sort_by_key(|attribute| attribute.location);
+    attributes
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum LayoutMismatch {
+    #[error("Shader input \"{name}\" at location {location} is not provided by the vertex layout")]
+    Missing { name: String, location: GLint },
+    #[error(
+        "Shader input \"{name}\" at location {location} is a {} but the vertex layout provides \"{attribute}\" there as a {}",
+        gl_type_name(*shader_type), gl_type_name(*layout_type)
+    )]
+    WrongType { name: String, location: GLint, shader_type: GLenum, attribute: &'static str, layout_type: GLenum },
+}
+
+/// Every mismatch found by [`validate_layout`]
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub struct LayoutError(pub Vec<LayoutMismatch>);
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Vertex layout does not match the shader:")?;
+        for mismatch in &self.0 {
+            write!(f, "\n    {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every active shader input is fed by an attribute of the matching type at the same location.
+///
+/// Layout attributes the shader doesn't read are fine, the driver simply ignores them.
+pub fn validate_layout(inputs: &[AttributeInfo], layout: &[VertexAttribute]) -> Result<(), LayoutError> {
+    let mismatches: Vec<LayoutMismatch> = inputs.iter()
+        .filter(|input| input.location >= 0)
+        .filter_map(|input| {
+            let Some(attribute) = layout.iter().find(|attribute| attribute.location as GLint == input.location) else {
+                return Some(LayoutMismatch::Missing { name: input.name.clone(), location: input.location });
+            };
+            (attribute.shader_type() != input.gl_type).then(|| LayoutMismatch::WrongType {
+                name: input.name.clone(),
+                location: input.location,
+                shader_type: input.gl_type,
+                attribute: attribute.name,
+                layout_type: attribute.shader_type(),
+            })
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(LayoutError(mismatches))
+    }
+}
@@ -1,35 +1,378 @@
-use block_mesh::{Voxel, MergeVoxel, VoxelVisibility};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use thiserror::Error;
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub enum BlockType {
-    Air = 0,
-    Stone = 1,
-    Grass = 2,
+use super::light::MAX_LIGHT;
+
+/// Index of a block's properties in the [`BlockRegistry`]
+pub type BlockId = u16;
+
+/// How much light and sight a block lets through
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Deserialize)]
+pub enum Opacity {
+    /// Nothing is drawn, e.g. air
+    Transparent,
+    /// Drawn, but faces behind it are still visible, e.g. glass or water
+    Translucent,
+    /// Drawn and hides everything behind it
+    Opaque,
 }
 
-impl Default for BlockType {
-    fn default() -> Self {
-        Self::Stone
+/// The six faces of a block, in the order `block_mesh`'s `RIGHT_HANDED_Y_UP_CONFIG` emits them
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum BlockFace {
+    /// -X
+    West = 0,
+    /// -Y
+    Bottom = 1,
+    /// -Z
+    North = 2,
+    /// +X
+    East = 3,
+    /// +Y
+    Top = 4,
+    /// +Z
+    South = 5,
+}
+
+impl BlockFace {
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::West,
+        BlockFace::Bottom,
+        BlockFace::North,
+        BlockFace::East,
+        BlockFace::Top,
+        BlockFace::South,
+    ];
+
+    /// The face on the other side of the block
+    pub fn opposite(self) -> Self {
+        BlockFace::ALL[(self as usize + 3) % 6]
     }
+
+    /// The unit vector pointing out of this face
+    pub fn normal(self) -> [i32; 3] {
+        match self {
+            BlockFace::West => [-1, 0, 0],
+            BlockFace::Bottom => [0, -1, 0],
+            BlockFace::North => [0, 0, -1],
+            BlockFace::East => [1, 0, 0],
+            BlockFace::Top => [0, 1, 0],
+            BlockFace::South => [0, 0, 1],
+        }
+    }
+}
+
+/// Texture names for each face of a block, indexed by [`BlockFace`]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct BlockTextures(pub [String; 6]);
+
+impl BlockTextures {
+    pub fn all(name: &str) -> Self {
+        Self(std::array::from_fn(|_| String::from(name)))
+    }
+
+    pub fn top_bottom_sides(top: &str, bottom: &str, sides: &str) -> Self {
+        let mut textures = Self::all(sides);
+        textures.0[BlockFace::Top as usize] = String::from(top);
+        textures.0[BlockFace::Bottom as usize] = String::from(bottom);
+        textures
+    }
+
+    pub fn get(&self, face: BlockFace) -> &str {
+        &self.0[face as usize]
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockProperties {
+    pub name: String,
+    pub opacity: Opacity,
+    /// Whether entities collide with the block
+    pub solid: bool,
+    pub textures: BlockTextures,
+    /// Light level given off by the block, 0 for none up to [`MAX_LIGHT`]
+    pub light_emission: u8,
+    /// How long the block takes to break, negative for unbreakable
+    pub hardness: f32,
+}
+
+impl BlockProperties {
+    pub fn air() -> Self {
+        Self {
+            name: String::from(AIR_NAME),
+            opacity: Opacity::Transparent,
+            solid: false,
+            textures: BlockTextures::default(),
+            light_emission: 0,
+            hardness: 0.0,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.opacity != Opacity::Transparent
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BlockRegistryError {
+    #[error("A block named \"{0}\" is already registered")]
+    DuplicateName(String),
+    #[error("Cannot register more than {} blocks", BlockId::MAX as usize + 1)]
+    Full,
+    #[error("Block \"{block}\" emits light level {level}, the brightest is {}", MAX_LIGHT)]
+    LightEmissionTooHigh { block: String, level: u8 },
+}
+
+pub const AIR_NAME: &str = "air";
+
+/// Block textures are looked up as `<name>.png` next to the definitions file
+pub const TEXTURE_EXTENSION: &str = "png";
+
+#[derive(Debug, Error)]
+pub enum BlockDefinitionError {
+    #[error("Unable to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Line {line}, column {column}: {message}")]
+    Parse { line: usize, column: usize, message: String },
+    #[error("Block \"{block}\" has no texture for its {face:?} face")]
+    MissingTexture { block: String, face: BlockFace },
+    #[error("Block \"{block}\" uses texture \"{texture}\" but {path} does not exist")]
+    TextureNotFound { block: String, texture: String, path: PathBuf },
+    #[error(transparent)]
+    Registry(#[from] BlockRegistryError),
+}
+
+/// Textures of a block as written in a definitions file.
+///
+/// The most specific entry wins: a named face, then `sides` for the four horizontal faces, then `all`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureDefinition {
+    all: Option<String>,
+    sides: Option<String>,
+    top: Option<String>,
+    bottom: Option<String>,
+    north: Option<String>,
+    south: Option<String>,
+    east: Option<String>,
+    west: Option<String>,
+}
+
+impl TextureDefinition {
+    fn get(&self, face: BlockFace) -> Option<&String> {
+        let (specific, sides) = match face {
+            BlockFace::Top => (&self.top, &None),
+            BlockFace::Bottom => (&self.bottom, &None),
+            BlockFace::North => (&self.north, &self.sides),
+            BlockFace::South => (&self.south, &self.sides),
+            BlockFace::East => (&self.east, &self.sides),
+            BlockFace::West => (&self.west, &self.sides),
+        };
+        specific.as_ref().or(sides.as_ref()).or(self.all.as_ref())
+    }
+}
+
+/// A single entry of a definitions file, see `blocks.ron`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockDefinition {
+    name: String,
+    textures: TextureDefinition,
+    transparency: Opacity,
+    collision: bool,
+    #[serde(default)]
+    light_emission: u8,
+    #[serde(default = "default_hardness")]
+    hardness: f32,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
-pub struct Block(pub BlockType);
+fn default_hardness() -> f32 {
+    1.0
+}
 
-impl Voxel for Block {
-    fn get_visibility(&self) -> VoxelVisibility {
-        if self.0 != BlockType::Air {
-            VoxelVisibility::Opaque
-        } else {
-            VoxelVisibility::Empty
+impl BlockDefinition {
+    fn into_properties(self, texture_dir: &Path) -> Result<BlockProperties, BlockDefinitionError> {
+        let mut textures = BlockTextures::default();
+        for face in BlockFace::ALL {
+            let texture = self.textures.get(face).ok_or_else(|| BlockDefinitionError::MissingTexture {
+                block: self.name.clone(),
+                face
+            })?;
+            let path = texture_path(texture_dir, texture);
+            if !path.is_file() {
+                return Err(BlockDefinitionError::TextureNotFound {
+                    block: self.name,
+                    texture: texture.clone(),
+                    path
+                });
+            }
+            textures.0[face as usize] = texture.clone();
         }
+
+        Ok(BlockProperties {
+            name: self.name,
+            opacity: self.transparency,
+            solid: self.collision,
+            textures,
+            light_emission: self.light_emission,
+            hardness: self.hardness,
+        })
     }
 }
 
-impl MergeVoxel for Block {
-    type MergeValue = bool;
+/// Where the image for texture `name` lives
+pub fn texture_path(texture_dir: &Path, name: &str) -> PathBuf {
+    texture_dir.join(format!("{}.{}", name, TEXTURE_EXTENSION))
+}
+
+/// Maps block ids to their properties.
+///
+/// Air is always registered first so that [`Block::AIR`] and zeroed chunks are empty.
+/// Every distinct texture of a visible block is given a layer of the block texture array as it is registered.
+#[derive(Debug, Clone)]
+pub struct BlockRegistry {
+    blocks: Vec<BlockProperties>,
+    ids: HashMap<String, BlockId>,
+    /// texture names, indexed by layer
+    textures: Vec<String>,
+    /// texture layer of each face, indexed by block id then [`BlockFace`]
+    face_layers: Vec<[u32; 6]>,
+}
+
+impl BlockRegistry {
+    /// A registry holding only air
+    pub fn new() -> Self {
+        let mut registry = Self {
+            blocks: Vec::new(),
+            ids: HashMap::new(),
+            textures: Vec::new(),
+            face_layers: Vec::new(),
+        };
+        registry.register(BlockProperties::air()).unwrap();
+        registry
+    }
 
-    fn merge_value(&self) -> Self::MergeValue {
-        self.0 != BlockType::Air
+    /// Loads a RON list of block definitions, resolving textures relative to the file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BlockDefinitionError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| BlockDefinitionError::Io {
+            path: path.to_path_buf(),
+            source
+        })?;
+        Self::from_definitions(&source, path.parent().unwrap_or(Path::new(".")))
     }
-}
\ No newline at end of file
+
+    /// Parses a RON list of block definitions, registering them after air in the order they are written
+    pub fn from_definitions(source: &str, texture_dir: &Path) -> Result<Self, BlockDefinitionError> {
+        // lets textures be written as `top: "grass_top"` rather than `top: Some("grass_top")`
+        let options = ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        let definitions: Vec<BlockDefinition> = options.from_str(source).map_err(|err| BlockDefinitionError::Parse {
+            line: err.position.line,
+            column: err.position.col,
+            message: err.code.to_string()
+        })?;
+
+        let mut registry = Self::new();
+        for definition in definitions {
+            registry.register(definition.into_properties(texture_dir)?)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, properties: BlockProperties) -> Result<Block, BlockRegistryError> {
+        if self.ids.contains_key(&properties.name) {
+            return Err(BlockRegistryError::DuplicateName(properties.name));
+        }
+        if properties.light_emission > MAX_LIGHT {
+            return Err(BlockRegistryError::LightEmissionTooHigh { block: properties.name, level: properties.light_emission });
+        }
+        let id = BlockId::try_from(self.blocks.len()).map_err(|_| BlockRegistryError::Full)?;
+        let mut layers = [0; 6];
+        if properties.is_visible() {
+            for face in BlockFace::ALL {
+                layers[face as usize] = self.texture_layer_of(properties.textures.get(face));
+            }
+        }
+        self.ids.insert(properties.name.clone(), id);
+        self.blocks.push(properties);
+        self.face_layers.push(layers);
+        Ok(Block(id))
+    }
+
+    fn texture_layer_of(&mut self, texture: &str) -> u32 {
+        match self.textures.iter().position(|name| name == texture) {
+            Some(layer) => layer as u32,
+            None => {
+                self.textures.push(String::from(texture));
+                self.textures.len() as u32 - 1
+            }
+        }
+    }
+
+    /// Layer of the block texture array drawn on `face` of `block`
+    pub fn texture_layer(&self, block: Block, face: BlockFace) -> u32 {
+        self.face_layers.get(block.0 as usize).map_or(0, |layers| layers[face as usize])
+    }
+
+    /// Names of every texture used by a visible block, in layer order
+    pub fn texture_names(&self) -> &[String] {
+        &self.textures
+    }
+
+    /// Looks a block up by name
+    pub fn block(&self, name: &str) -> Option<Block> {
+        self.ids.get(name).map(|id| Block(*id))
+    }
+
+    /// Properties of `block`, unknown ids are treated as air
+    pub fn get(&self, block: Block) -> &BlockProperties {
+        self.blocks.get(block.0 as usize).unwrap_or(&self.blocks[Block::AIR.0 as usize])
+    }
+}
+
+impl Default for BlockRegistry {
+    /// The built in blocks
+    fn default() -> Self {
+        let mut registry = Self::new();
+        for properties in [
+            BlockProperties {
+                name: String::from("stone"),
+                opacity: Opacity::Opaque,
+                solid: true,
+                textures: BlockTextures::all("stone"),
+                light_emission: 0,
+                hardness: 1.5,
+            },
+            BlockProperties {
+                name: String::from("dirt"),
+                opacity: Opacity::Opaque,
+                solid: true,
+                textures: BlockTextures::all("dirt"),
+                light_emission: 0,
+                hardness: 0.5,
+            },
+            BlockProperties {
+                name: String::from("grass"),
+                opacity: Opacity::Opaque,
+                solid: true,
+                textures: BlockTextures::top_bottom_sides("grass_top", "dirt", "grass_side"),
+                light_emission: 0,
+                hardness: 0.6,
+            },
+        ] {
+            registry.register(properties).unwrap();
+        }
+        registry
+    }
+}
+
+/// A compact handle to a block type, look its properties up in the [`BlockRegistry`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct Block(pub BlockId);
+
+impl Block {
+    pub const AIR: Block = Block(0);
+}
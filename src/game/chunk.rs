@@ -1,16 +1,50 @@
-use glm::{I32Vec3, Vec3, Vec2};
+use glm::I32Vec3;
 use noise::{Fbm, Perlin, NoiseFn};
-use block_mesh::{greedy_quads, GreedyQuadsBuffer, ndshape::{ConstShape, ConstShape3u32}, RIGHT_HANDED_Y_UP_CONFIG, QuadCoordinateConfig};
-use crate::engine::mesh::{Mesh, Vertex, Texture};
+use block_mesh::{greedy_quads_with_merge_strategy, visible_block_faces, FaceStrides, GreedyQuadsBuffer, MergeStrategy, OrientedBlockFace, UnitQuadBuffer, UnorientedQuad, ndshape::{ConstShape, ConstShape3u32}, RIGHT_HANDED_Y_UP_CONFIG, Voxel, VoxelVisibility};
+use crate::engine::mesh::{Mesh, MeshData};
 
-use super::block::{Block, BlockType};
+use super::block::{Block, BlockFace, BlockRegistry, Opacity};
+use super::chunk_vertex::ChunkVertex;
+use super::light::{ChunkLight, LightLevel, PaddedLight};
+use super::visibility::FaceConnectivity;
+use super::WORLD_HEIGHT;
 
 pub type ChunkShape = ConstShape3u32<16, 16, 16>;
+/// A chunk with a one block border on every side, the border only decides which faces are visible
+pub type PaddedChunkShape = ConstShape3u32<18, 18, 18>;
+
+/// The voxels of a single chunk
+pub type Blocks = [Block; ChunkShape::SIZE as usize];
+/// The voxels of a chunk plus the touching layer of each of its neighbours
+pub type PaddedBlocks = [Block; PaddedChunkShape::SIZE as usize];
+
+/// Offsets to the 26 chunks sharing a face, edge or corner with a chunk
+pub fn neighbour_offsets() -> impl Iterator<Item = [i32; 3]> {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| [x, y, z])))
+        .filter(|offset| *offset != [0; 3])
+}
+
+/// How a chunk's visible faces are turned into quads
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum MeshingMode {
+    /// Merges neighbouring identical faces into as few quads as possible
+    #[default]
+    Greedy,
+    /// One quad per visible face, slower to draw but useful for debugging and comparison
+    Simple,
+}
 
 pub struct Chunk {
     pub position: I32Vec3,
-    pub blocks: [Block; ChunkShape::SIZE as usize],
-    pub mesh: Mesh
+    pub blocks: Blocks,
+    /// sky and block light of each voxel, kept up to date by the [`World`](super::world::World) holding the chunk
+    pub light: ChunkLight,
+    /// `None` until the chunk has been meshed with its neighbours and uploaded
+    pub mesh: Option<Mesh<ChunkVertex>>,
+    pub meshing: MeshingMode,
+    /// which faces can see each other through the chunk, updated whenever it is meshed
+    pub connectivity: FaceConnectivity
 }
 
 impl Default for Chunk {
@@ -18,108 +52,308 @@ impl Default for Chunk {
         Self {
             position: Default::default(),
             blocks: [Block::default(); ChunkShape::SIZE as usize],
-            mesh: Mesh::default()
+            light: [LightLevel::DARK; ChunkShape::SIZE as usize],
+            mesh: None,
+            meshing: MeshingMode::default(),
+            connectivity: FaceConnectivity::default()
+        }
+    }
+}
+
+/// The voxels of a chunk generated off the main thread, not yet meshed
+pub struct GeneratedChunk {
+    pub position: I32Vec3,
+    pub blocks: Blocks
+}
+
+impl GeneratedChunk {
+    pub fn generate(position: I32Vec3, noise: &Fbm<Perlin>, registry: &BlockRegistry) -> Self {
+        Self {
+            position,
+            blocks: Chunk::generate_blocks(position, noise, registry)
+        }
+    }
+}
+
+/// Mesh data built off the main thread, ready to be uploaded to the GPU
+pub struct MeshedChunk {
+    pub position: I32Vec3,
+    pub mesh: MeshData<ChunkVertex>,
+    pub connectivity: FaceConnectivity
+}
+
+impl MeshedChunk {
+    pub fn mesh(position: I32Vec3, padded: &PaddedBlocks, light: &PaddedLight, registry: &BlockRegistry, mode: MeshingMode) -> Self {
+        Self {
+            position,
+            mesh: Chunk::mesh_padded(padded, light, registry, mode),
+            connectivity: FaceConnectivity::from_padded(padded, registry)
+        }
+    }
+}
+
+/// A block as the mesher sees it, with its properties already looked up in the registry
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct MeshVoxel {
+    block: Block,
+    visibility: VoxelVisibility,
+    light: LightLevel
+}
+
+impl MeshVoxel {
+    fn new(block: Block, light: LightLevel, registry: &BlockRegistry) -> Self {
+        let visibility = match registry.get(block).opacity {
+            Opacity::Transparent => VoxelVisibility::Empty,
+            Opacity::Translucent => VoxelVisibility::Translucent,
+            Opacity::Opaque => VoxelVisibility::Opaque,
+        };
+        Self { block, visibility, light }
+    }
+}
+
+impl Voxel for MeshVoxel {
+    fn get_visibility(&self) -> VoxelVisibility {
+        self.visibility
+    }
+}
+
+/// Ambient occlusion of each corner of the face at `index`, from 0 (fully occluded) to 3 (open).
+///
+/// Corners are in the order `block_mesh` gives quad corners in. Each is darkened by the two voxels beside it and the one
+/// diagonal to it in the layer in front of the face, and fully occluded when both sides are solid.
+fn ambient_occlusion(voxels: &[MeshVoxel], index: u32, face_strides: &FaceStrides) -> [u8; 4] {
+    let FaceStrides { u_stride, v_stride, visibility_offset, .. } = *face_strides;
+    let front = index.wrapping_add(visibility_offset);
+    let occludes = |du: i32, dv: i32| {
+        let neighbour = front.wrapping_add((u_stride as i32 * du) as u32).wrapping_add((v_stride as i32 * dv) as u32);
+        voxels[neighbour as usize].visibility == VoxelVisibility::Opaque
+    };
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(du, dv)| {
+        let (side_u, side_v, corner) = (occludes(du, 0), occludes(0, dv), occludes(du, dv));
+        if side_u && side_v { 0 } else { 3 - side_u as u8 - side_v as u8 - corner as u8 }
+    })
+}
+
+/// Strides between neighbouring voxels on a face, in padded voxel indices
+fn face_strides(face: &OrientedBlockFace) -> FaceStrides {
+    let unit = UnorientedQuad { minimum: [1; 3], width: 1, height: 1 };
+    let [corner, u_corner, v_corner, _] = face.quad_corners(&unit).map(|c| PaddedChunkShape::linearize(c.to_array()));
+    let normal = face.signed_normal().to_array();
+    let visibility_offset = PaddedChunkShape::linearize(normal.map(|n| n.unsigned_abs()));
+    FaceStrides {
+        n_stride: visibility_offset,
+        u_stride: u_corner - corner,
+        v_stride: v_corner - corner,
+        visibility_offset: if normal.iter().sum::<i32>() < 0 { visibility_offset.wrapping_neg() } else { visibility_offset },
+    }
+}
+
+/// Greedy merging that only joins faces of the same block type with the same light and ambient occlusion,
+/// so a quad never spans two different textures or shades.
+///
+/// Faces whose occlusion changes along an axis are not merged along it, as a larger quad would stretch the gradient.
+struct LitFaceMerger;
+
+impl LitFaceMerger {
+    /// What has to match for two faces to share a quad: the block, the light of the voxel in front of the face and its corners' occlusion
+    unsafe fn face_value(voxels: &[MeshVoxel], index: u32, face_strides: &FaceStrides) -> (Block, LightLevel, [u8; 4]) {
+        let front = voxels.get_unchecked(index.wrapping_add(face_strides.visibility_offset) as usize);
+        (voxels.get_unchecked(index as usize).block, front.light, ambient_occlusion(voxels, index, face_strides))
+    }
+
+    /// The same test `block_mesh` uses: not yet meshed, and not hidden by the voxel in front
+    unsafe fn face_needs_mesh(voxels: &[MeshVoxel], index: u32, visibility_offset: u32, visited: &[bool]) -> bool {
+        let voxel = voxels.get_unchecked(index as usize);
+        if voxel.visibility == VoxelVisibility::Empty || visited[index as usize] {
+            return false;
+        }
+        match voxels.get_unchecked(index.wrapping_add(visibility_offset) as usize).visibility {
+            VoxelVisibility::Empty => true,
+            VoxelVisibility::Translucent => voxel.visibility == VoxelVisibility::Opaque,
+            VoxelVisibility::Opaque => false,
+        }
+    }
+
+    unsafe fn row_width(voxels: &[MeshVoxel], visited: &[bool], value: (Block, LightLevel, [u8; 4]), start: u32, face_strides: &FaceStrides, max_width: u32) -> u32 {
+        let mut width = 0;
+        let mut index = start;
+        while width < max_width
+            && Self::face_needs_mesh(voxels, index, face_strides.visibility_offset, visited)
+            && Self::face_value(voxels, index, face_strides) == value
+        {
+            width += 1;
+            index = index.wrapping_add(face_strides.u_stride);
+        }
+        width
+    }
+}
+
+impl MergeStrategy for LitFaceMerger {
+    type Voxel = MeshVoxel;
+
+    unsafe fn find_quad(min_index: u32, max_width: u32, max_height: u32, face_strides: &FaceStrides, voxels: &[MeshVoxel], visited: &[bool]) -> (u32, u32) {
+        let value = Self::face_value(voxels, min_index, face_strides);
+        let [ao_00, ao_10, ao_01, ao_11] = value.2;
+        let max_width = if ao_00 == ao_10 && ao_01 == ao_11 { max_width } else { 1 };
+        let max_height = if ao_00 == ao_01 && ao_10 == ao_11 { max_height } else { 1 };
+        // as wide as possible along u, then as many rows of that width as possible along v
+        let width = Self::row_width(voxels, visited, value, min_index, face_strides, max_width);
+        let mut height = 1;
+        let mut row = min_index.wrapping_add(face_strides.v_stride);
+        while height < max_height && Self::row_width(voxels, visited, value, row, face_strides, width) == width {
+            height += 1;
+            row = row.wrapping_add(face_strides.v_stride);
+        }
+        (width, height)
+    }
+}
+
+/// Copies a chunk into the middle of a padded array and fills the border from its neighbours.
+///
+/// `neighbour` looks up the chunk at an offset from [`neighbour_offsets`], edge and corner neighbours
+/// are needed for ambient occlusion along the border. Missing neighbours leave their part of the border as air
+/// so faces against them stay visible until they load.
+pub fn pad_blocks<'a>(blocks: &Blocks, neighbour: impl Fn([i32; 3]) -> Option<&'a Blocks>) -> PaddedBlocks {
+    pad(blocks, neighbour, Block::AIR)
+}
 
+/// Pads a chunk's light like [`pad_blocks`], missing neighbours light their part of the border as open sky
+pub fn pad_light<'a>(light: &ChunkLight, neighbour: impl Fn([i32; 3]) -> Option<&'a ChunkLight>) -> PaddedLight {
+    pad(light, neighbour, LightLevel::SKY)
+}
+
+fn pad<'a, T: Copy + 'a>(
+    voxels: &[T; ChunkShape::SIZE as usize],
+    neighbour: impl Fn([i32; 3]) -> Option<&'a [T; ChunkShape::SIZE as usize]>,
+    missing: T
+) -> [T; PaddedChunkShape::SIZE as usize] {
+    let mut padded = [missing; PaddedChunkShape::SIZE as usize];
+    for (i, voxel) in voxels.iter().enumerate() {
+        let [x, y, z] = ChunkShape::delinearize(i as u32);
+        padded[PaddedChunkShape::linearize([x + 1, y + 1, z + 1]) as usize] = *voxel;
+    }
+
+    for offset in neighbour_offsets() {
+        let Some(neighbour) = neighbour(offset) else {
+            continue;
+        };
+        // along each axis, the neighbour's voxels touching this chunk and how far they move to sit in the padding
+        let [(xs, dx), (ys, dy), (zs, dz)] = offset.map(|o| match o {
+            -1 => (15..16, -15),
+            0 => (0..16, 1),
+            _ => (0..1, 17),
+        });
+        for x in xs {
+            for y in ys.clone() {
+                for z in zs.clone() {
+                    let to = [x as i32 + dx, y as i32 + dy, z as i32 + dz].map(|c| c as u32);
+                    padded[PaddedChunkShape::linearize(to) as usize] = neighbour[ChunkShape::linearize([x, y, z]) as usize];
+                }
+            }
         }
     }
+    padded
 }
 
 impl Chunk {
-    pub fn gen(position: I32Vec3, noise: &Fbm<Perlin>) -> Self {
+    pub fn new(position: I32Vec3, blocks: Blocks) -> Self {
+        Self {
+            position,
+            blocks,
+            light: [LightLevel::DARK; ChunkShape::SIZE as usize],
+            mesh: None,
+            meshing: MeshingMode::default(),
+            connectivity: FaceConnectivity::default()
+        }
+    }
+
+    /// The GPU stage of chunk creation, must be called on the thread that owns the GL context
+    pub fn upload_mesh(&mut self, meshed: MeshedChunk) {
+        self.mesh = match self.mesh.take() {
+            // chunks of nothing but air, or buried in rock, have nothing to draw
+            _ if meshed.mesh.is_empty() => None,
+            Some(mut mesh) => {
+                mesh.update(meshed.mesh);
+                Some(mesh)
+            },
+            None => Some(meshed.mesh.upload())
+        };
+        self.connectivity = meshed.connectivity;
+    }
+
+    pub fn generate_blocks(position: I32Vec3, noise: &Fbm<Perlin>, registry: &BlockRegistry) -> Blocks {
         let x_offset = position.x * 16;
         let y_offset = position.y * 16;
         let z_offset = position.z * 16;
-        let mut blocks = [Block::default(); ChunkShape::SIZE as usize];
+        let stone = registry.block("stone").unwrap_or(Block::AIR);
+        let mut blocks = [Block::AIR; ChunkShape::SIZE as usize];
         for i in 0..ChunkShape::SIZE {
             let [x, y, z] = ChunkShape::delinearize(i);
-            blocks[i as usize] = if noise.get([(x as i32 + x_offset) as f64, (y as i32 + y_offset) as f64, (z as i32 + z_offset) as f64]).trunc() > -1.0 {
-                blocks[i as usize]
+            blocks[i as usize] = if y as i32 + y_offset < WORLD_HEIGHT && noise.get([(x as i32 + x_offset) as f64, (y as i32 + y_offset) as f64, (z as i32 + z_offset) as f64]).trunc() > -1.0 {
+                stone
             } else {
-                Block(BlockType::Air)
+                Block::AIR
             }
         };
+        blocks
+    }
 
-        let mut buffer = GreedyQuadsBuffer::new(blocks.len());
-        greedy_quads(&blocks, &ChunkShape {}, [0; 3], [15; 3], &RIGHT_HANDED_Y_UP_CONFIG.faces, &mut buffer);
-        let num_indices = buffer.quads.num_quads() * 6;
-        let num_vertices = buffer.quads.num_quads() * 4;
-        let mut indices = Vec::with_capacity(num_indices);
-        let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
-        let mut textures: Vec<Texture> = Vec::new();
-        textures.push(Texture { id: 0, type_: String::from("texture_diffuse"), path: String::from("dirt.png") });
-        println!("quads created: {}", buffer.quads.num_quads());
-
-        for (group, face) in buffer.quads.groups.into_iter().zip(RIGHT_HANDED_Y_UP_CONFIG.faces.into_iter()) {
-            for (num, quad) in group.into_iter().enumerate() {
-                indices.extend_from_slice(&face.quad_mesh_indices(num as u32));
-                let position = &face.quad_mesh_positions(&quad, 1.0);
-                let normal = &face.quad_mesh_normals();
-                let tex_coord = &face.tex_coords(RIGHT_HANDED_Y_UP_CONFIG.u_flip_face, true, &quad);
-                for j in 0..3 {
-                    vertices.push(Vertex {
-                        position: Vec3::from(position[j]),
-                        normal: Vec3::from(normal[j]),
-                        texCoords: Vec2::from(tex_coord[j]),
-                        ..Default::default()
-                    });
-                }
-                //positions.extend_from_slice(&face.quad_mesh_positions(&quad, 1.0));
-                //normals.extend_from_slice(&face.quad_mesh_normals());
-                //textures.extend_from_slice(&face.tex_coords(RIGHT_HANDED_Y_UP_CONFIG.u_flip_face, true, &quad))
+    /// Meshes a chunk on its own, as if every neighbour were air and everything lit by open sky
+    pub fn mesh_blocks(blocks: &Blocks, registry: &BlockRegistry, mode: MeshingMode) -> MeshData<ChunkVertex> {
+        Self::mesh_padded(&pad_blocks(blocks, |_| None), &[LightLevel::SKY; PaddedChunkShape::SIZE as usize], registry, mode)
+    }
 
+    /// Builds the chunk's mesh data without touching GL, the border only culls faces and is never meshed itself.
+    ///
+    /// Each face is lit by the light of the voxel in front of it and each vertex shaded by its ambient occlusion.
+    pub fn mesh_padded(padded: &PaddedBlocks, light: &PaddedLight, registry: &BlockRegistry, mode: MeshingMode) -> MeshData<ChunkVertex> {
+        let voxels: Vec<MeshVoxel> = padded.iter().zip(light).map(|(block, light)| MeshVoxel::new(*block, *light, registry)).collect();
+        let faces = &RIGHT_HANDED_Y_UP_CONFIG.faces;
+        // quads grouped by face, in the same order as `faces`
+        let groups: [Vec<UnorientedQuad>; 6] = match mode {
+            MeshingMode::Greedy => {
+                let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+                greedy_quads_with_merge_strategy::<_, _, LitFaceMerger>(&voxels, &PaddedChunkShape {}, [0; 3], [17; 3], faces, &mut buffer);
+                buffer.quads.groups
+            },
+            MeshingMode::Simple => {
+                let mut buffer = UnitQuadBuffer::new();
+                visible_block_faces(&voxels, &PaddedChunkShape {}, [0; 3], [17; 3], faces, &mut buffer);
+                buffer.groups.map(|group| group.into_iter().map(UnorientedQuad::from).collect())
             }
-        }
-
-        let mut mesh: Mesh = Mesh::new(vertices, indices, textures);
+        };
+        let num_quads: usize = groups.iter().map(Vec::len).sum();
+        let mut indices = Vec::with_capacity(num_quads * 6);
+        let mut vertices: Vec<ChunkVertex> = Vec::with_capacity(num_quads * 4);
 
-        Self {
-            position,
-            blocks,
-            mesh
+        for ((group, face), block_face) in groups.into_iter().zip(faces).zip(BlockFace::ALL) {
+            let strides = face_strides(face);
+            for quad in group.into_iter() {
+                let index = PaddedChunkShape::linearize(quad.minimum);
+                let block = padded[index as usize];
+                let layer = registry.texture_layer(block, block_face);
+                let face_light = light[index.wrapping_add(strides.visibility_offset) as usize];
+                // merged faces all share the occlusion of the first
+                let occlusion = ambient_occlusion(&voxels, index, &strides);
+                // split along the diagonal joining the brighter pair of corners, otherwise the shading
+                // depends on which way round the quad is. Starting from another corner swaps the diagonal.
+                let corners = if occlusion[0] + occlusion[3] > occlusion[1] + occlusion[2] { [1, 3, 0, 2] } else { [0, 1, 2, 3] };
+                // indices are relative to the whole mesh, not to the face group
+                indices.extend_from_slice(&face.quad_mesh_indices(vertices.len() as u32));
+                let positions = face.quad_mesh_positions(&quad, 1.0);
+                let tex_coords = face.tex_coords(RIGHT_HANDED_Y_UP_CONFIG.u_flip_face, true, &quad);
+                for corner in corners {
+                    // undo the padding so the chunk spans 0..16, every coordinate is a whole number
+                    let position = positions[corner].map(|p| p as u32 - 1);
+                    let vertex = ChunkVertex::new(position, block_face, tex_coords[corner].map(|t| t as u32), layer)
+                        .set_light(face_light)
+                        .set_occlusion(occlusion[corner]);
+                    vertices.push(vertex);
+                }
+            }
         }
-    }
 
-    pub unsafe fn create_mesh(&mut self, id: u32) -> u32 {
-        id
-    }
-}
-
-//pub struct Chunk {
-//    pub position: I32Vec3,
-//    pub blocks: Vec<Vec<Vec<Block>>>,
-//    pub mesh: Vec<f32>
-//}
-//
-//impl Chunk {
-//    pub fn gen(position: I32Vec3, noise: &Fbm<Perlin>) -> Self {
-//        let x_offset = position.x * 16;
-//        let y_offset = position.y * 16;
-//        let z_offset = position.z * 16;
-//        let mut blocks: Vec<Vec<Vec<Block>>> = Vec::new();
-//        for x_pos in 0usize..=15 {
-//            blocks.push(Vec::new());
-//            for y_pos in 0usize..=15 {
-//                blocks.push(Vec::new());
-//                for z_pos in 0usize..=15 {
-//                    blocks[x_pos][y_pos].push(Block(
-//                        if noise.get([
-//                            (x_pos as i32 + x_offset) as f64,
-//                            (y_pos as i32 + y_offset) as f64,
-//                            (z_pos as i32 + z_offset) as f64
-//                            ]).trunc() == -1.0_f64 {BlockType::Air} else {BlockType::Grass},
-//                            false 
-//                    )
-//                )}
-//            }
-//        }
-//        Self {
-//            position,
-//            blocks,
-//            mesh: vec![]
-//        }
-//    }
-//
-//    pub fn update(&mut self, position: I32Vec3, result: Block) {
-//        self.blocks[position.x as usize][position.y as usize][position.z as usize] = result
-//    }
-//}
\ No newline at end of file
+        // chunks sample the block texture array bound by the renderer rather than per mesh textures
+        MeshData::new(vertices, indices, Vec::new())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::vertex_layout;
+
+use super::block::BlockFace;
+use super::light::LightLevel;
+
+const COORDINATE_BITS: u32 = 5;
+#[cfg(test)]
+const COORDINATE_MASK: u32 = (1 << COORDINATE_BITS) - 1;
+const FACE_SHIFT: u32 = COORDINATE_BITS * 3;
+const LAYER_SHIFT: u32 = COORDINATE_BITS * 2;
+const LAYER_MASK: u32 = (1 << 16) - 1;
+const LIGHT_SHIFT: u32 = FACE_SHIFT + 3;
+const LIGHT_MASK: u32 = (1 << 8) - 1;
+const OCCLUSION_SHIFT: u32 = LIGHT_SHIFT + 8;
+const OCCLUSION_MASK: u32 = 3;
+
+/// A chunk vertex packed into two `u32`s, unpacked again by `chunk.vert`.
+///
+/// `data[0]` holds the position within the chunk in bits 0..15 (5 bits per axis, 0 to 16) and the
+/// [`BlockFace`] in bits 15..18, then the [`LightLevel`] in bits 18..26 (block light below sky light)
+/// and the ambient occlusion in bits 26..28. `data[1]` holds the texture coordinates in bits 0..10 (5 bits each,
+/// repeating across greedy quads) and the block texture layer in bits 10..26. The remaining bits are free.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChunkVertex {
+    pub data: [u32; 2],
+}
+
+vertex_layout! {
+    ChunkVertex {
+        0 => data: [gl::UNSIGNED_INT; 2] as Integer,
+    }
+}
+
+impl ChunkVertex {
+    pub const MAX_COORDINATE: u32 = 16;
+    pub const MAX_LAYER: u32 = LAYER_MASK;
+    pub const MAX_OCCLUSION: u32 = OCCLUSION_MASK;
+
+    pub fn new(position: [u32; 3], face: BlockFace, uv: [u32; 2], layer: u32) -> Self {
+        debug_assert!(position.iter().chain(&uv).all(|c| *c <= Self::MAX_COORDINATE), "{:?} {:?} outside of a chunk", position, uv);
+        debug_assert!(layer <= Self::MAX_LAYER, "texture layer {} does not fit", layer);
+        let [x, y, z] = position;
+        let [u, v] = uv;
+        Self {
+            data: [
+                x | y << COORDINATE_BITS | z << (COORDINATE_BITS * 2) | (face as u32) << FACE_SHIFT,
+                u | v << COORDINATE_BITS | layer << LAYER_SHIFT,
+            ]
+        }
+    }
+
+    // chunk.vert does the unpacking in the game, reading fields back is only needed by tests
+
+    /// Position within the chunk, each axis 0 to 16
+    #[cfg(test)]
+    pub fn position(&self) -> [u32; 3] {
+        [0, 1, 2].map(|axis| self.data[0] >> (COORDINATE_BITS * axis) & COORDINATE_MASK)
+    }
+
+    #[cfg(test)]
+    pub fn face(&self) -> BlockFace {
+        BlockFace::ALL[(self.data[0] >> FACE_SHIFT & 7) as usize]
+    }
+
+    #[cfg(test)]
+    pub fn uv(&self) -> [u32; 2] {
+        [0, 1].map(|axis| self.data[1] >> (COORDINATE_BITS * axis) & COORDINATE_MASK)
+    }
+
+    /// Layer of the block texture array
+    #[cfg(test)]
+    pub fn layer(&self) -> u32 {
+        self.data[1] >> LAYER_SHIFT & LAYER_MASK
+    }
+
+    pub fn set_light(mut self, light: LightLevel) -> Self {
+        let packed = (light.sky() as u32) << 4 | light.block() as u32;
+        self.data[0] = self.data[0] & !(LIGHT_MASK << LIGHT_SHIFT) | packed << LIGHT_SHIFT;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn light(&self) -> LightLevel {
+        let packed = self.data[0] >> LIGHT_SHIFT & LIGHT_MASK;
+        LightLevel::new((packed >> 4) as u8, (packed & 15) as u8)
+    }
+
+    /// `occlusion` goes from 0, a corner boxed in by solid blocks, to [`ChunkVertex::MAX_OCCLUSION`] for an open one
+    pub fn set_occlusion(mut self, occlusion: u8) -> Self {
+        debug_assert!(occlusion as u32 <= Self::MAX_OCCLUSION, "ambient occlusion {} out of range", occlusion);
+        self.data[0] = self.data[0] & !(OCCLUSION_MASK << OCCLUSION_SHIFT) | (occlusion as u32) << OCCLUSION_SHIFT;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn occlusion(&self) -> u8 {
+        (self.data[0] >> OCCLUSION_SHIFT & OCCLUSION_MASK) as u8
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use block_mesh::ndshape::ConstShape;
+use nalgebra_glm::I32Vec3;
+
+use super::block::{BlockFace, BlockRegistry, Opacity};
+use super::chunk::{Chunk, ChunkShape, PaddedChunkShape};
+use super::world::{chunks_touching, split_block_position};
+use super::{CHUNK_SIZE, WORLD_HEIGHT};
+
+/// The brightest a voxel can be lit, light drops by one for every block it spreads
+pub const MAX_LIGHT: u8 = 15;
+
+/// The two kinds of light, which spread independently
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LightChannel {
+    /// Comes down from open sky above [`WORLD_HEIGHT`], without dimming while it travels straight down
+    Sky,
+    /// Given off by blocks with a `light_emission`
+    Block,
+}
+
+impl LightChannel {
+    pub const ALL: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+}
+
+/// Sky and block light of one voxel, 0 to [`MAX_LIGHT`] each, packed into a byte
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct LightLevel(u8);
+
+impl LightLevel {
+    pub const DARK: Self = Self(0);
+    /// Open sky and no block light, what faces against unloaded chunks are lit with
+    pub const SKY: Self = Self(MAX_LIGHT << 4);
+
+    /// Levels above [`MAX_LIGHT`] are clamped
+    pub fn new(sky: u8, block: u8) -> Self {
+        Self(sky.min(MAX_LIGHT) << 4 | block.min(MAX_LIGHT))
+    }
+
+    pub fn sky(self) -> u8 {
+        self.0 >> 4
+    }
+
+    pub fn block(self) -> u8 {
+        self.0 & MAX_LIGHT
+    }
+
+    pub fn get(self, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => self.sky(),
+            LightChannel::Block => self.block(),
+        }
+    }
+
+    pub fn with(self, channel: LightChannel, level: u8) -> Self {
+        match channel {
+            LightChannel::Sky => Self::new(level, self.block()),
+            LightChannel::Block => Self::new(self.sky(), level),
+        }
+    }
+}
+
+/// The light of each voxel of a chunk, indexed like [`Chunk::blocks`]
+pub type ChunkLight = [LightLevel; ChunkShape::SIZE as usize];
+/// The light of a chunk plus the touching layer of each of its neighbours, indexed like `PaddedBlocks`
+pub type PaddedLight = [LightLevel; PaddedChunkShape::SIZE as usize];
+
+/// Light changes still waiting to be spread, kept between frames so a large change can be spread over several
+#[derive(Debug, Default)]
+pub struct LightQueue {
+    /// voxels whose light should spread to their neighbours, per channel
+    spread: [VecDeque<I32Vec3>; 2],
+    /// voxels that were darkened and the level they had, per channel
+    removed: [VecDeque<(I32Vec3, u8)>; 2],
+    /// chunks holding, or touching, a voxel whose light changed
+    changed: HashSet<I32Vec3>,
+}
+
+impl LightQueue {
+    /// Whether every queued change has been carried out
+    pub fn is_empty(&self) -> bool {
+        self.spread.iter().all(VecDeque::is_empty) && self.removed.iter().all(VecDeque::is_empty)
+    }
+
+    /// The loaded chunks whose light changed since the last call, and so need re-meshing
+    pub fn take_changed(&mut self) -> HashSet<I32Vec3> {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+/// Spreads changes in light breadth first through the loaded chunks, crossing chunk borders.
+///
+/// Queue changes with [`LightPropagation::light_chunk`], [`LightPropagation::unlight_chunk`] and
+/// [`LightPropagation::block_changed`], then [`LightPropagation::run`] removes light that lost its source before
+/// spreading light from every source still there. Light never spreads into unloaded chunks.
+pub struct LightPropagation<'a> {
+    chunks: &'a mut HashMap<I32Vec3, Chunk>,
+    registry: &'a BlockRegistry,
+    queue: &'a mut LightQueue,
+}
+
+impl<'a> LightPropagation<'a> {
+    pub fn new(chunks: &'a mut HashMap<I32Vec3, Chunk>, registry: &'a BlockRegistry, queue: &'a mut LightQueue) -> Self {
+        Self { chunks, registry, queue }
+    }
+
+    /// Queues the light sources of a newly inserted chunk along with the light its neighbours shine into it
+    pub fn light_chunk(&mut self, chunk_position: &I32Vec3) {
+        let origin = chunk_position * CHUNK_SIZE;
+        for i in 0..ChunkShape::SIZE {
+            let [x, y, z] = ChunkShape::delinearize(i);
+            let position = origin + I32Vec3::new(x as i32, y as i32, z as i32);
+            for channel in LightChannel::ALL {
+                let source = self.source_level(&position, channel);
+                self.seed(&position, channel, source);
+            }
+            for face in BlockFace::ALL {
+                let neighbour = position + I32Vec3::from(face.normal());
+                if split_block_position(&neighbour).0 != *chunk_position {
+                    for channel in LightChannel::ALL {
+                        self.queue.spread[channel as usize].push_back(neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues the light an unloaded chunk shone into its loaded neighbours to be removed,
+    /// `removed` must already be out of the loaded chunks
+    pub fn unlight_chunk(&mut self, removed: &Chunk) {
+        let origin = removed.position * CHUNK_SIZE;
+        for (i, light) in removed.light.iter().enumerate() {
+            let [x, y, z] = ChunkShape::delinearize(i as u32);
+            let position = origin + I32Vec3::new(x as i32, y as i32, z as i32);
+            let on_border = BlockFace::ALL.iter()
+                .any(|face| split_block_position(&(position + I32Vec3::from(face.normal()))).0 != removed.position);
+            if !on_border {
+                continue;
+            }
+            // darkening the voxel as it leaves darkens whatever it lit on the other side of the border
+            for channel in LightChannel::ALL {
+                let level = light.get(channel);
+                if level > 0 {
+                    self.queue.removed[channel as usize].push_back((position, level));
+                }
+            }
+        }
+    }
+
+    /// Queues the changes caused by replacing the block at `position`
+    pub fn block_changed(&mut self, position: &I32Vec3) {
+        for channel in LightChannel::ALL {
+            self.remove(position, channel);
+            // light around the block flows back in if the new block lets it through
+            for face in BlockFace::ALL {
+                self.queue.spread[channel as usize].push_back(position + I32Vec3::from(face.normal()));
+            }
+            let source = self.source_level(position, channel);
+            self.seed(position, channel, source);
+        }
+    }
+
+    /// Carries out queued changes until at most `steps` voxels have been visited, returning whether every change
+    /// has been carried out. Chunks whose light changed are collected in the queue's [`LightQueue::take_changed`].
+    pub fn run(mut self, mut steps: usize) -> bool {
+        for channel in LightChannel::ALL {
+            let queue = channel as usize;
+            loop {
+                if steps == 0 {
+                    return self.queue.is_empty();
+                }
+                steps -= 1;
+                if let Some((position, level)) = self.queue.removed[queue].pop_front() {
+                    self.darken_around(channel, &position, level);
+                } else if let Some(position) = self.queue.spread[queue].pop_front() {
+                    self.spread_from(channel, &position);
+                } else {
+                    break;
+                }
+            }
+        }
+        true
+    }
+
+    /// Removes the light a darkened voxel gave its neighbours, queueing any lit by something else to spread back
+    fn darken_around(&mut self, channel: LightChannel, position: &I32Vec3, level: u8) {
+        for face in BlockFace::ALL {
+            let neighbour = position + I32Vec3::from(face.normal());
+            let Some(neighbour_level) = self.light(&neighbour).map(|light| light.get(channel)) else {
+                continue;
+            };
+            if neighbour_level == 0 {
+                continue;
+            }
+            if neighbour_level < level || spreads_undimmed(channel, face, level) && neighbour_level == MAX_LIGHT {
+                // lit by the removed light, so it goes too
+                self.remove(&neighbour, channel);
+                let source = self.source_level(&neighbour, channel);
+                self.seed(&neighbour, channel, source);
+            } else {
+                // lit by something else, which may now reach back into the darkened area
+                self.queue.spread[channel as usize].push_back(neighbour);
+            }
+        }
+    }
+
+    /// Raises each neighbour the voxel's light reaches to the level it arrives at
+    fn spread_from(&mut self, channel: LightChannel, position: &I32Vec3) {
+        let Some(level) = self.light(position).map(|light| light.get(channel)) else {
+            return;
+        };
+        for face in BlockFace::ALL {
+            let next = if spreads_undimmed(channel, face, level) { level } else { level.saturating_sub(1) };
+            let neighbour = position + I32Vec3::from(face.normal());
+            if next == 0 || !self.transmits(&neighbour) {
+                continue;
+            }
+            if self.light(&neighbour).is_some_and(|light| light.get(channel) < next) {
+                self.set_light(&neighbour, channel, next);
+                self.queue.spread[channel as usize].push_back(neighbour);
+            }
+        }
+    }
+
+    fn light(&self, position: &I32Vec3) -> Option<LightLevel> {
+        let (chunk, local) = split_block_position(position);
+        self.chunks.get(&chunk).map(|chunk| chunk.light[ChunkShape::linearize(local) as usize])
+    }
+
+    fn set_light(&mut self, position: &I32Vec3, channel: LightChannel, level: u8) {
+        let (chunk_position, local) = split_block_position(position);
+        let Some(chunk) = self.chunks.get_mut(&chunk_position) else {
+            return;
+        };
+        let light = &mut chunk.light[ChunkShape::linearize(local) as usize];
+        if light.get(channel) == level {
+            return;
+        }
+        *light = light.with(channel, level);
+        for touching in chunks_touching(position) {
+            if self.chunks.contains_key(&touching) {
+                self.queue.changed.insert(touching);
+            }
+        }
+    }
+
+    /// Darkens a voxel, queueing the light it had so whatever it lit is darkened too
+    fn remove(&mut self, position: &I32Vec3, channel: LightChannel) {
+        let Some(level) = self.light(position).map(|light| light.get(channel)).filter(|level| *level > 0) else {
+            return;
+        };
+        self.set_light(position, channel, 0);
+        self.queue.removed[channel as usize].push_back((*position, level));
+    }
+
+    /// Raises a voxel to `level` if it is darker, queueing it to spread
+    fn seed(&mut self, position: &I32Vec3, channel: LightChannel, level: u8) {
+        if level > 0 && self.light(position).is_some_and(|light| light.get(channel) < level) {
+            self.set_light(position, channel, level);
+            self.queue.spread[channel as usize].push_back(*position);
+        }
+    }
+
+    /// The light a voxel gives itself, regardless of its neighbours
+    fn source_level(&self, position: &I32Vec3, channel: LightChannel) -> u8 {
+        let (chunk_position, local) = split_block_position(position);
+        let Some(chunk) = self.chunks.get(&chunk_position) else {
+            return 0;
+        };
+        let properties = self.registry.get(chunk.blocks[ChunkShape::linearize(local) as usize]);
+        match channel {
+            LightChannel::Block => properties.light_emission,
+            LightChannel::Sky => {
+                // decided by height alone, so sky light doesn't come and go as chunks above stream in and out
+                if position.y >= WORLD_HEIGHT && properties.opacity != Opacity::Opaque { MAX_LIGHT } else { 0 }
+            }
+        }
+    }
+
+    /// Whether light can enter the block at `position`, false if it isn't loaded
+    fn transmits(&self, position: &I32Vec3) -> bool {
+        let (chunk, local) = split_block_position(position);
+        self.chunks.get(&chunk)
+            .is_some_and(|chunk| self.registry.get(chunk.blocks[ChunkShape::linearize(local) as usize]).opacity != Opacity::Opaque)
+    }
+}
+
+/// Full skylight travels straight down forever, everything else dims by one per block
+fn spreads_undimmed(channel: LightChannel, face: BlockFace, level: u8) -> bool {
+    channel == LightChannel::Sky && face == BlockFace::Bottom && level == MAX_LIGHT
+}
@@ -1,9 +1,20 @@
 pub mod world;
 pub mod chunk;
+pub mod chunk_vertex;
 pub mod block;
 pub mod player;
+pub mod light;
+pub mod sun;
+pub mod visibility;
+pub mod worker;
 
 use world::World;
+use nalgebra_glm::{I32Vec3, Vec3, vec3};
+
+/// Width of a chunk in blocks along each axis
+pub const CHUNK_SIZE: i32 = 16;
+/// World space height of the top of the world, nothing is generated at or above it and everything there is open sky
+pub const WORLD_HEIGHT: i32 = 2 * CHUNK_SIZE;
 
 pub struct Game {
     pub world: World,
@@ -24,15 +35,12 @@ pub struct Renderer {
 
 }
 
-pub fn get_bounds(x: u32) -> ((i32, i32, i32), (i32, i32, i32)) {
-    let upper_bounds: (i32, i32, i32);
-    let result = x as i32 / 2;
-    let lower_bounds = (-result, -result, -result);
-    let mod_result = x as i32 % 2;
-    if mod_result == 1 {
-        upper_bounds = (result, result, result);
-    } else {
-        upper_bounds = (result - 1, result - 1, result - 1)
-    }
-    (lower_bounds, upper_bounds)
-}
\ No newline at end of file
+/// Returns the position of the chunk containing the world space `position`
+pub fn chunk_position_of(position: &Vec3) -> I32Vec3 {
+    let size = CHUNK_SIZE as f32;
+    vec3(
+        (position.x / size).floor() as i32,
+        (position.y / size).floor() as i32,
+        (position.z / size).floor() as i32
+    )
+}
@@ -0,0 +1,43 @@
+use cgmath::{ElementWise, InnerSpace, Vector3};
+
+/// Directional light lighting the terrain, sent to shaders through the per-frame uniform block
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Sun {
+    /// unit vector the light travels along, pointing away from the sun
+    pub direction: Vector3<f32>,
+    pub colour: Vector3<f32>,
+    /// light every face receives, even facing away from the sun
+    pub ambient: Vector3<f32>,
+}
+
+impl Default for Sun {
+    /// Late morning light, so the top and two of the sides of a block are each shaded differently
+    fn default() -> Self {
+        Self {
+            direction: Vector3::new(-0.4, -1.0, -0.3).normalize(),
+            colour: Vector3::new(0.8, 0.78, 0.7),
+            ambient: Vector3::new(0.35, 0.37, 0.42),
+        }
+    }
+}
+
+impl Sun {
+    /// `direction` needn't be normalised
+    pub fn set_direction(self, direction: Vector3<f32>) -> Self {
+        Self { direction: direction.normalize(), ..self }
+    }
+
+    pub fn set_colour(self, colour: Vector3<f32>) -> Self {
+        Self { colour, ..self }
+    }
+
+    pub fn set_ambient(self, ambient: Vector3<f32>) -> Self {
+        Self { ambient, ..self }
+    }
+
+    /// Light reaching a face with unit `normal`, matching the Lambert term in `chunk.frag`
+    pub fn light(&self, normal: Vector3<f32>) -> Vector3<f32> {
+        let diffuse = normal.dot(-self.direction).max(0.0);
+        self.ambient.add_element_wise(self.colour * diffuse)
+    }
+}
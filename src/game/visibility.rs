@@ -0,0 +1,82 @@
+use block_mesh::ndshape::ConstShape;
+
+use super::block::{Block, BlockFace, BlockRegistry, Opacity};
+use super::chunk::{ChunkShape, PaddedBlocks, PaddedChunkShape};
+
+/// Which pairs of a chunk's six faces are joined by a path through blocks that can be seen through.
+///
+/// Used to walk outwards from the camera without entering chunks hidden behind solid rock.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FaceConnectivity {
+    /// a bit per [`BlockFace`] for each face, set if the two are connected
+    connections: [u8; 6],
+}
+
+impl Default for FaceConnectivity {
+    /// Every face connected, the safe assumption for a chunk that hasn't been meshed yet
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl FaceConnectivity {
+    pub const ALL: Self = Self { connections: [0b111111; 6] };
+    pub const NONE: Self = Self { connections: [0; 6] };
+
+    pub fn connects(&self, from: BlockFace, to: BlockFace) -> bool {
+        self.connections[from as usize] & (1 << to as u8) != 0
+    }
+
+    /// Only looks at the chunk in the middle of `padded`, the border belongs to its neighbours
+    pub fn from_padded(padded: &PaddedBlocks, registry: &BlockRegistry) -> Self {
+        Self::flood(|[x, y, z]| padded[PaddedChunkShape::linearize([x + 1, y + 1, z + 1]) as usize], registry)
+    }
+
+    /// Flood fills each pocket of see-through blocks, connecting every face the pocket touches to each other
+    fn flood(block_at: impl Fn([u32; 3]) -> Block, registry: &BlockRegistry) -> Self {
+        let open: Vec<bool> = (0..ChunkShape::SIZE)
+            .map(|i| registry.get(block_at(ChunkShape::delinearize(i))).opacity != Opacity::Opaque)
+            .collect();
+        let mut visited = vec![false; ChunkShape::SIZE as usize];
+        let mut connectivity = Self::NONE;
+        let mut stack = Vec::new();
+
+        for start in 0..ChunkShape::SIZE as usize {
+            if !open[start] || visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            stack.push(start as u32);
+            let mut faces = 0u8;
+            while let Some(index) = stack.pop() {
+                let position = ChunkShape::delinearize(index);
+                for face in BlockFace::ALL {
+                    let Some(next) = step(position, face) else {
+                        faces |= 1 << face as u8;
+                        continue;
+                    };
+                    let next = ChunkShape::linearize(next);
+                    if open[next as usize] && !visited[next as usize] {
+                        visited[next as usize] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            for face in BlockFace::ALL {
+                if faces & (1 << face as u8) != 0 {
+                    connectivity.connections[face as usize] |= faces;
+                }
+            }
+        }
+        connectivity
+    }
+}
+
+/// The neighbouring position across `face`, `None` if that leaves the chunk
+fn step(position: [u32; 3], face: BlockFace) -> Option<[u32; 3]> {
+    let mut next = position;
+    for (axis, offset) in face.normal().into_iter().enumerate() {
+        next[axis] = next[axis].checked_add_signed(offset).filter(|p| *p < 16)?;
+    }
+    Some(next)
+}
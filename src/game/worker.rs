@@ -0,0 +1,169 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+use nalgebra_glm::I32Vec3;
+use noise::{Fbm, Perlin};
+
+use super::block::BlockRegistry;
+use super::chunk::{GeneratedChunk, MeshedChunk, MeshingMode, PaddedBlocks};
+use super::light::PaddedLight;
+
+/// Shared flag used to abandon a job whose chunk has since been unloaded.
+///
+/// Each job gets its own token, so it also identifies which job a result came from.
+pub type CancelToken = Arc<AtomicBool>;
+
+enum ChunkTask {
+    Generate,
+    Mesh(Box<PaddedBlocks>, Box<PaddedLight>, MeshingMode)
+}
+
+struct ChunkJob {
+    position: I32Vec3,
+    task: ChunkTask,
+    cancelled: CancelToken
+}
+
+/// A finished job along with the token it was submitted with
+pub struct ChunkResult<T> {
+    pub position: I32Vec3,
+    pub token: CancelToken,
+    pub output: T
+}
+
+/// A fixed set of threads that generate and mesh chunks off the main thread.
+///
+/// Jobs are handed out over a shared channel. Generated chunks and meshes come back over a channel each,
+/// so they can be collected at their own pace, leaving only the GPU upload to be done by the thread that owns
+/// the GL context.
+pub struct ChunkWorkerPool {
+    sender: Option<Sender<ChunkJob>>,
+    generated: Receiver<ChunkResult<Box<GeneratedChunk>>>,
+    meshed: Receiver<ChunkResult<MeshedChunk>>,
+    workers: Vec<JoinHandle<()>>
+}
+
+/// Where the workers send finished jobs
+struct ResultSenders {
+    generated: Sender<ChunkResult<Box<GeneratedChunk>>>,
+    meshed: Sender<ChunkResult<MeshedChunk>>
+}
+
+impl ChunkWorkerPool {
+    pub fn new(threads: usize, noise: Fbm<Perlin>, registry: Arc<BlockRegistry>) -> Self {
+        let (sender, jobs) = channel::<ChunkJob>();
+        let (generated_sender, generated) = channel();
+        let (meshed_sender, meshed) = channel();
+        let jobs = Arc::new(Mutex::new(jobs));
+        let noise = Arc::new(noise);
+
+        let workers = (0..threads.max(1)).map(|i| {
+            let jobs = Arc::clone(&jobs);
+            let noise = Arc::clone(&noise);
+            let registry = Arc::clone(&registry);
+            let results = ResultSenders { generated: generated_sender.clone(), meshed: meshed_sender.clone() };
+            thread::Builder::new()
+                .name(format!("chunk-worker-{}", i))
+                .spawn(move || worker_loop(&jobs, &noise, &registry, &results))
+                .expect("Failed to spawn chunk worker thread")
+        }).collect();
+
+        Self {
+            sender: Some(sender),
+            generated,
+            meshed,
+            workers
+        }
+    }
+
+    /// Uses one worker per available core, leaving one for the main thread
+    pub fn with_available_parallelism(noise: Fbm<Perlin>, registry: Arc<BlockRegistry>) -> Self {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(2);
+        Self::new(threads.saturating_sub(1), noise, registry)
+    }
+
+    /// Queues a chunk for generation, returning the token that cancels it
+    pub fn submit_generate(&self, position: I32Vec3) -> CancelToken {
+        self.submit(position, ChunkTask::Generate)
+    }
+
+    /// Queues a padded chunk for meshing, returning the token that cancels it
+    pub fn submit_mesh(&self, position: I32Vec3, padded: Box<PaddedBlocks>, light: Box<PaddedLight>, mode: MeshingMode) -> CancelToken {
+        self.submit(position, ChunkTask::Mesh(padded, light, mode))
+    }
+
+    fn submit(&self, position: I32Vec3, task: ChunkTask) -> CancelToken {
+        let cancelled = CancelToken::default();
+        if let Some(sender) = &self.sender {
+            // the workers only hang up when the pool is dropped
+            let _ = sender.send(ChunkJob { position, task, cancelled: Arc::clone(&cancelled) });
+        }
+        cancelled
+    }
+
+    /// Returns a generated chunk if one is ready, without blocking
+    pub fn try_recv_generated(&self) -> Option<ChunkResult<Box<GeneratedChunk>>> {
+        try_recv(&self.generated)
+    }
+
+    /// Returns a finished mesh if one is ready, without blocking
+    pub fn try_recv_meshed(&self) -> Option<ChunkResult<MeshedChunk>> {
+        try_recv(&self.meshed)
+    }
+}
+
+impl Drop for ChunkWorkerPool {
+    fn drop(&mut self) {
+        // closing the job channel makes every worker fall out of its loop
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn try_recv<T>(results: &Receiver<T>) -> Option<T> {
+    match results.try_recv() {
+        Ok(result) => Some(result),
+        Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None
+    }
+}
+
+fn worker_loop(jobs: &Mutex<Receiver<ChunkJob>>, noise: &Fbm<Perlin>, registry: &BlockRegistry, results: &ResultSenders) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return
+        };
+        let Ok(job) = job else {
+            return;
+        };
+
+        if job.cancelled.load(Ordering::Relaxed) {
+            continue;
+        }
+        let sent = match &job.task {
+            ChunkTask::Generate => {
+                let output = Box::new(GeneratedChunk::generate(job.position, noise, registry));
+                send(&results.generated, job.position, job.cancelled, output)
+            },
+            ChunkTask::Mesh(padded, light, mode) => {
+                let output = MeshedChunk::mesh(job.position, padded, light, registry, *mode);
+                send(&results.meshed, job.position, job.cancelled, output)
+            }
+        };
+        if !sent {
+            return;
+        }
+    }
+}
+
+/// Sends a finished job unless it was cancelled while running, returning false once the pool has hung up
+fn send<T>(results: &Sender<ChunkResult<T>>, position: I32Vec3, token: CancelToken, output: T) -> bool {
+    if token.load(Ordering::Relaxed) {
+        return true;
+    }
+    results.send(ChunkResult { position, token, output }).is_ok()
+}
@@ -1,20 +1,81 @@
-use super::get_bounds;
-use nalgebra_glm::{I32Vec3, vec3};
+use super::{chunk_position_of, CHUNK_SIZE};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::{Values, ValuesMut};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use nalgebra_glm::{I32Vec3, Vec3, vec3};
+use cgmath::Point3;
 use noise::{Fbm, Perlin, Seedable};
-use super::chunk::Chunk;
+use super::block::{Block, BlockFace, BlockRegistry};
+use super::chunk::{Chunk, ChunkShape, MeshedChunk, MeshingMode, PaddedBlocks, pad_blocks, pad_light, neighbour_offsets};
+use super::light::{LightLevel, LightPropagation, LightQueue, PaddedLight};
+use block_mesh::ndshape::ConstShape;
 use super::player::Player;
+use crate::engine::frustum::{Aabb, Frustum};
+use super::worker::{ChunkWorkerPool, CancelToken, ChunkResult};
+
+const DEFAULT_RENDER_DISTANCE: usize = 4;
+const DEFAULT_GENERATION_BUDGET: usize = 2;
+/// Maximum number of finished meshes uploaded in a single frame
+const MESH_UPLOAD_BUDGET: usize = 16;
+/// Voxels light may be spread to per frame, a little over what lighting a single chunk takes
+const LIGHT_BUDGET: usize = 16384;
 
-#[derive(Default)]
 pub struct World {
-    pub chunks: Vec<Vec<Vec<Chunk>>>,
-    pub lower_size: (i32, i32, i32),
-    pub upper_size: (i32, i32, i32),
+    chunks: HashMap<I32Vec3, Chunk>,
     pub player: Player,
     pub current_chunk: I32Vec3,
     noise: Fbm<Perlin>,
+    registry: Arc<BlockRegistry>,
     render_distance: usize,
     seed: u32,
-    initial_size: u32,
+    /// chunk positions waiting to be generated, nearest first
+    load_queue: VecDeque<I32Vec3>,
+    /// maximum number of chunks dispatched to, and uploaded from, the workers in a single frame
+    generation_budget: usize,
+    /// the chunk the visible set was last calculated around
+    last_streamed_chunk: Option<I32Vec3>,
+    /// started on first use so that it picks up the seeded noise function
+    workers: Option<ChunkWorkerPool>,
+    /// chunks currently being generated by the workers
+    pending: HashMap<I32Vec3, CancelToken>,
+    /// chunks currently being meshed by the workers
+    pending_meshes: HashMap<I32Vec3, CancelToken>,
+    /// loaded chunks whose mesh is missing or out of date with their neighbours
+    remesh_queue: HashSet<I32Vec3>,
+    /// how newly loaded chunks are meshed
+    meshing_mode: MeshingMode,
+    /// chunks with edited blocks, re-meshed on the main thread by the next update
+    dirty: HashSet<I32Vec3>,
+    /// light changes from loading and unloading chunks, spread a budget at a time by each update
+    light_queue: LightQueue,
+    /// light changes from editing blocks, kept apart from the streaming queue so an edit only spreads its own changes
+    edit_light_queue: LightQueue,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            player: Player::default(),
+            current_chunk: Default::default(),
+            noise: Fbm::default(),
+            registry: Arc::new(BlockRegistry::default()),
+            render_distance: DEFAULT_RENDER_DISTANCE,
+            seed: 0,
+            load_queue: VecDeque::new(),
+            generation_budget: DEFAULT_GENERATION_BUDGET,
+            last_streamed_chunk: None,
+            workers: None,
+            pending: HashMap::new(),
+            pending_meshes: HashMap::new(),
+            remesh_queue: HashSet::new(),
+            meshing_mode: MeshingMode::default(),
+            dirty: HashSet::new(),
+            light_queue: LightQueue::default(),
+            edit_light_queue: LightQueue::default(),
+        }
+    }
 }
 
 impl World {
@@ -22,43 +83,451 @@ impl World {
         Self {
             noise: self.noise.set_seed(seed),
             seed,
+            workers: None,
             ..self
         }
     }
 
-    pub fn initial_gen(self, initial_size: u32) -> Self {
-        let (upper_size, lower_size) = get_bounds(initial_size);
-        let mut chunks: Vec<Vec<Vec<Chunk>>> = Vec::new();
+    /// Replaces the block registry, must be done before any chunks are generated
+    pub fn set_registry(self, registry: BlockRegistry) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            workers: None,
+            ..self
+        }
+    }
+
+    pub fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+
+    pub fn get_chunk(&self, position: &I32Vec3) -> Option<&Chunk> {
+        self.chunks.get(position)
+    }
+
+    pub fn get_chunk_mut(&mut self, position: &I32Vec3) -> Option<&mut Chunk> {
+        self.chunks.get_mut(position)
+    }
+
+    pub fn contains_chunk(&self, position: &I32Vec3) -> bool {
+        self.chunks.contains_key(position)
+    }
+
+    pub fn chunks(&self) -> Values<'_, I32Vec3, Chunk> {
+        self.chunks.values()
+    }
+
+    pub fn chunks_mut(&mut self) -> ValuesMut<'_, I32Vec3, Chunk> {
+        self.chunks.values_mut()
+    }
+
+    /// Inserts a chunk at its own position, returning the chunk it replaced (if any).
+    ///
+    /// The chunk and its neighbours are queued to be re-meshed against each other. Lighting the chunk, and spreading
+    /// its neighbours' light into it, is queued for [`World::propagate_light`].
+    pub fn insert_chunk(&mut self, chunk: Chunk) -> Option<Chunk> {
+        let position = chunk.position;
+        let replaced = self.chunks.remove(&position);
+        if let Some(replaced) = &replaced {
+            self.light().unlight_chunk(replaced);
+        }
+        self.chunks.insert(position, chunk);
+        self.mark_for_remesh(&position);
+        self.light().light_chunk(&position);
+        replaced
+    }
+
+    fn light(&mut self) -> LightPropagation<'_> {
+        LightPropagation::new(&mut self.chunks, &self.registry, &mut self.light_queue)
+    }
+
+    /// Spreads queued light changes to at most `steps` voxels, queueing every chunk whose light changed to be re-meshed.
+    /// Returns whether all of the queued changes have been carried out
+    pub fn propagate_light(&mut self, steps: usize) -> bool {
+        let settled = self.light().run(steps);
+        let relit = self.light_queue.take_changed();
+        self.remesh_queue.extend(relit);
+        settled
+    }
+
+    /// Sky and block light at a world space block position, `None` if its chunk isn't loaded
+    pub fn get_light(&self, position: &I32Vec3) -> Option<LightLevel> {
+        let (chunk, local) = split_block_position(position);
+        self.chunks.get(&chunk).map(|chunk| chunk.light[ChunkShape::linearize(local) as usize])
+    }
+
+    /// Unloads a chunk, queueing the light it shone into its neighbours to be removed and every chunk touching it
+    /// to be re-meshed without it
+    pub fn remove_chunk(&mut self, position: &I32Vec3) -> Option<Chunk> {
+        self.remesh_queue.remove(position);
+        self.dirty.remove(position);
+        if let Some(cancelled) = self.pending_meshes.remove(position) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        let removed = self.chunks.remove(position)?;
+        self.light().unlight_chunk(&removed);
+        self.mark_for_remesh(position);
+        Some(removed)
+    }
+
+    /// Queues a chunk and every loaded chunk sharing a face, edge or corner with it to be re-meshed,
+    /// call this whenever the chunk's blocks change
+    pub fn mark_for_remesh(&mut self, position: &I32Vec3) {
+        for offset in neighbour_offsets().chain([[0; 3]]) {
+            let neighbour = position + I32Vec3::from(offset);
+            if self.chunks.contains_key(&neighbour) {
+                self.remesh_queue.insert(neighbour);
+            }
+        }
+    }
+
+    /// The chunk's blocks surrounded by the touching layer of each loaded neighbour
+    pub fn padded_blocks(&self, position: &I32Vec3) -> Option<Box<PaddedBlocks>> {
+        let chunk = self.chunks.get(position)?;
+        let neighbour = |offset| self.chunks.get(&(position + I32Vec3::from(offset))).map(|neighbour| &neighbour.blocks);
+        Some(Box::new(pad_blocks(&chunk.blocks, neighbour)))
+    }
+
+    /// The chunk's light surrounded by the touching layer of each loaded neighbour's light
+    pub fn padded_light(&self, position: &I32Vec3) -> Option<Box<PaddedLight>> {
+        let chunk = self.chunks.get(position)?;
+        let neighbour = |offset| self.chunks.get(&(position + I32Vec3::from(offset))).map(|neighbour| &neighbour.light);
+        Some(Box::new(pad_light(&chunk.light, neighbour)))
+    }
+
+    /// The block at a world space block position, `None` if its chunk isn't loaded
+    pub fn get_block(&self, position: &I32Vec3) -> Option<Block> {
+        let (chunk, local) = split_block_position(position);
+        self.chunks.get(&chunk).map(|chunk| chunk.blocks[ChunkShape::linearize(local) as usize])
+    }
+
+    /// Replaces the block at a world space block position, returning the block it replaced.
+    ///
+    /// The light around the block is updated straight away, leaving light queued by streaming chunks to
+    /// [`World::update`]. The owning chunk is marked dirty, along with any loaded
+    /// neighbour touching the block and any chunk whose light changed, so the edit shows up after the next
+    /// [`World::update`]. Returns `None` and does nothing if the chunk isn't loaded.
+    pub fn set_block(&mut self, position: &I32Vec3, block: Block) -> Option<Block> {
+        let (chunk_position, local) = split_block_position(position);
+        let chunk = self.chunks.get_mut(&chunk_position)?;
+        let index = ChunkShape::linearize(local) as usize;
+        let replaced = std::mem::replace(&mut chunk.blocks[index], block);
+        if replaced == block {
+            return Some(replaced);
+        }
+
+        for touching in chunks_touching(position) {
+            if self.chunks.contains_key(&touching) {
+                self.dirty.insert(touching);
+            }
+        }
+        let mut light = LightPropagation::new(&mut self.chunks, &self.registry, &mut self.edit_light_queue);
+        light.block_changed(position);
+        light.run(usize::MAX);
+        let relit = self.edit_light_queue.take_changed();
+        self.dirty.extend(relit);
+        Some(replaced)
+    }
+
+    pub fn is_dirty(&self, position: &I32Vec3) -> bool {
+        self.dirty.contains(position)
+    }
 
-        for x_pos in 1..=initial_size {
-            chunks.push(Vec::new());
-            for y_pos in 1..=initial_size {
-                chunks.push(Vec::new());
-                for z_pos in 1..=initial_size {
-                    chunks[x_pos as usize][y_pos as usize].push(Chunk::gen(vec3(x_pos as i32, y_pos as i32, z_pos as i32), &self.noise));
+    pub fn dirty_chunk_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// The chunks whose bounds intersect `frustum`, the rest can't be seen and needn't be drawn
+    pub fn visible_chunks<'a>(&'a self, frustum: &'a Frustum) -> impl Iterator<Item = &'a Chunk> {
+        self.chunks().filter(|chunk| frustum.intersects_aabb(&chunk_bounds(&chunk.position)))
+    }
+
+    /// The chunks that may be visible from `camera_position`, found by walking outwards from the camera's chunk.
+    ///
+    /// A neighbour is only entered if it lies in `frustum`, the walk never turns back towards the camera, and the
+    /// chunk being left connects the face it was entered through to the face leading on, so chunks sealed off by
+    /// solid rock are never reached. Unloaded chunks end the walk. Falls back to [`World::visible_chunks`] when
+    /// the camera's own chunk isn't loaded.
+    pub fn reachable_chunks<'a>(&'a self, camera_position: &Vec3, frustum: &'a Frustum) -> Vec<&'a Chunk> {
+        let start = chunk_position_of(camera_position);
+        let Some(start_chunk) = self.chunks.get(&start) else {
+            return self.visible_chunks(frustum).collect();
+        };
+        let mut reachable = vec![start_chunk];
+        let mut visited = HashSet::from([start]);
+        // each chunk with the face it was entered through and a bit for every direction stepped to reach it
+        let mut queue: VecDeque<(I32Vec3, Option<BlockFace>, u8)> = VecDeque::from([(start, None, 0)]);
+        while let Some((position, entered, directions)) = queue.pop_front() {
+            let chunk = &self.chunks[&position];
+            for face in BlockFace::ALL {
+                if directions & (1 << face.opposite() as u8) != 0 {
+                    continue;
+                }
+                if entered.is_some_and(|entered| !chunk.connectivity.connects(entered, face)) {
+                    continue;
                 }
+                let neighbour = position + I32Vec3::from(face.normal());
+                if visited.contains(&neighbour) || !frustum.intersects_aabb(&chunk_bounds(&neighbour)) {
+                    continue;
+                }
+                let Some(next) = self.chunks.get(&neighbour) else {
+                    continue;
+                };
+                visited.insert(neighbour);
+                reachable.push(next);
+                queue.push_back((neighbour, Some(face.opposite()), directions | 1 << face as u8));
             }
         }
+        reachable
+    }
 
-        Self {
-            chunks,
-            upper_size,
-            lower_size,
-            initial_size,
-            ..self
+    /// Moves the world's focus to the chunk containing `position`.
+    ///
+    /// The visible set is only recalculated when the player crosses into a different chunk,
+    /// after which up to `generation_budget` queued chunks are handed to the workers, finished chunks and meshes are
+    /// collected within their budgets, light is spread as far as the per-frame budget allows and any chunks needing
+    /// a new mesh are sent off.
+    /// Chunks dirtied by [`World::set_block`] are re-meshed and uploaded before returning.
+    pub fn update(&mut self, position: &Vec3) {
+        self.current_chunk = chunk_position_of(position);
+        if self.last_streamed_chunk != Some(self.current_chunk) {
+            self.calculate_visible_chunks();
+        }
+        self.process_load_queue();
+        self.receive_worker_results();
+        self.propagate_light(LIGHT_BUDGET);
+        self.dispatch_remeshes();
+        self.remesh_dirty_chunks();
+    }
+
+    /// Re-meshes every dirty chunk on the calling thread, which must own the GL context, and uploads the results
+    pub fn remesh_dirty_chunks(&mut self) {
+        for meshed in self.mesh_dirty_chunks() {
+            if let Some(chunk) = self.get_chunk_mut(&meshed.position) {
+                chunk.upload_mesh(meshed);
+            }
         }
     }
 
-    //pub fn generate_new_chunk(&mut self, position: I32Vec3) {
-    //    self.chunks[position.x as usize][position.y as usize].insert(position.z as usize, Chunk::gen(position, &self.noise))
-    //}
+    /// Meshes every dirty chunk without touching GL, clearing the dirty set.
+    ///
+    /// Any worker job still meshing one of these chunks is cancelled, as its result would be older than the edit.
+    pub fn mesh_dirty_chunks(&mut self) -> Vec<MeshedChunk> {
+        let positions: Vec<I32Vec3> = self.dirty.drain().collect();
+        let mut meshes = Vec::with_capacity(positions.len());
+        for position in positions {
+            let (Some(padded), Some(light), Some(chunk)) = (self.padded_blocks(&position), self.padded_light(&position), self.chunks.get(&position)) else {
+                continue;
+            };
+            self.remesh_queue.remove(&position);
+            if let Some(stale) = self.pending_meshes.remove(&position) {
+                stale.store(true, Ordering::Relaxed);
+            }
+            meshes.push(MeshedChunk::mesh(position, &padded, &light, &self.registry, chunk.meshing));
+        }
+        meshes
+    }
 
+    /// Recalculates which chunks lie within `render_distance` of `current_chunk`,
+    /// evicting loaded chunks outside of it, cancelling generation of ones that are no longer needed
+    /// and queueing missing ones nearest first
     pub fn calculate_visible_chunks(&mut self) {
+        let centre = self.current_chunk;
+        let visible = chunks_in_range(&centre, self.render_distance);
+
+        let evicted: Vec<I32Vec3> = self.chunks.keys().filter(|position| !visible.contains(position)).copied().collect();
+        for position in evicted {
+            self.remove_chunk(&position);
+        }
+        self.pending.retain(|position, cancelled| {
+            let keep = visible.contains(position);
+            if !keep {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+            keep
+        });
+
+        let mut missing: Vec<I32Vec3> = visible
+            .into_iter()
+            .filter(|position| !self.contains_chunk(position) && !self.pending.contains_key(position))
+            .collect();
+        missing.sort_by_key(|position| distance_squared(position, &centre));
+
+        self.load_queue = missing.into();
+        self.last_streamed_chunk = Some(centre);
+    }
+
+    /// Hands at most `generation_budget` chunks from the front of the load queue to the workers
+    pub fn process_load_queue(&mut self) {
+        for _ in 0..self.generation_budget {
+            let Some(position) = self.load_queue.pop_front() else {
+                break;
+            };
+            if !self.contains_chunk(&position) && !self.pending.contains_key(&position) {
+                let cancelled = self.workers().submit_generate(position);
+                self.pending.insert(position, cancelled);
+            }
+        }
+    }
+
+    /// Inserts at most `generation_budget` generated chunks and uploads at most `MESH_UPLOAD_BUDGET` finished meshes.
+    ///
+    /// Results from jobs that were cancelled or superseded after they finished are discarded without counting
+    /// against either budget.
+    pub fn receive_worker_results(&mut self) {
+        let mut inserted = 0;
+        while inserted < self.generation_budget {
+            let Some(result) = self.workers.as_ref().and_then(ChunkWorkerPool::try_recv_generated) else {
+                break;
+            };
+            if !claim(&mut self.pending, &result) {
+                continue;
+            }
+            let mut chunk = Chunk::new(result.output.position, result.output.blocks);
+            chunk.meshing = self.meshing_mode;
+            self.insert_chunk(chunk);
+            inserted += 1;
+        }
+
+        let mut uploaded = 0;
+        while uploaded < MESH_UPLOAD_BUDGET {
+            let Some(result) = self.workers.as_ref().and_then(ChunkWorkerPool::try_recv_meshed) else {
+                break;
+            };
+            if !claim(&mut self.pending_meshes, &result) {
+                continue;
+            }
+            if let Some(chunk) = self.get_chunk_mut(&result.position) {
+                chunk.upload_mesh(result.output);
+                uploaded += 1;
+            }
+        }
+    }
+
+    /// Sends every chunk queued for re-meshing to the workers along with its neighbours' borders,
+    /// cancelling any mesh job for it that is now out of date
+    pub fn dispatch_remeshes(&mut self) {
+        let positions: Vec<I32Vec3> = self.remesh_queue.drain().collect();
+        for position in positions {
+            let (Some(padded), Some(light), Some(chunk)) = (self.padded_blocks(&position), self.padded_light(&position), self.chunks.get(&position)) else {
+                continue;
+            };
+            let mode = chunk.meshing;
+            let cancelled = self.workers().submit_mesh(position, padded, light, mode);
+            if let Some(stale) = self.pending_meshes.insert(position, cancelled) {
+                stale.store(true, Ordering::Relaxed);
+            }
+        }
+    }
 
+    fn workers(&mut self) -> &ChunkWorkerPool {
+        let noise = &self.noise;
+        let registry = &self.registry;
+        self.workers.get_or_insert_with(|| ChunkWorkerPool::with_available_parallelism(noise.clone(), Arc::clone(registry)))
     }
 
     pub fn set_render_distance(&mut self, distance: usize) {
-        self.render_distance = distance
+        self.render_distance = distance;
+        // force the visible set to be rebuilt on the next update
+        self.last_streamed_chunk = None;
     }
 
-}
\ No newline at end of file
+    pub fn set_generation_budget(&mut self, budget: usize) {
+        self.generation_budget = budget
+    }
+
+    pub fn meshing_mode(&self) -> MeshingMode {
+        self.meshing_mode
+    }
+
+    /// Switches every loaded chunk, and every chunk loaded from now on, to `mode`
+    pub fn set_meshing_mode(&mut self, mode: MeshingMode) {
+        self.meshing_mode = mode;
+        let mut switched = Vec::new();
+        for chunk in self.chunks_mut().filter(|chunk| chunk.meshing != mode) {
+            chunk.meshing = mode;
+            switched.push(chunk.position);
+        }
+        self.remesh_queue.extend(switched);
+    }
+
+    /// Switches a single loaded chunk to `mode`, queueing it to be re-meshed if that changes anything.
+    /// Returns `false` if the chunk isn't loaded
+    pub fn set_chunk_meshing_mode(&mut self, position: &I32Vec3, mode: MeshingMode) -> bool {
+        let Some(chunk) = self.get_chunk_mut(position) else {
+            return false;
+        };
+        if chunk.meshing != mode {
+            chunk.meshing = mode;
+            self.remesh_queue.insert(*position);
+        }
+        true
+    }
+
+}
+
+/// Takes the job behind `result` off `pending`, returning false if it was cancelled or superseded since
+fn claim<T>(pending: &mut HashMap<I32Vec3, CancelToken>, result: &ChunkResult<T>) -> bool {
+    let current = pending.get(&result.position).is_some_and(|token| Arc::ptr_eq(token, &result.token));
+    if current {
+        pending.remove(&result.position);
+    }
+    current
+}
+
+/// Every chunk position within `distance` chunks (euclidean) of `centre`
+pub fn chunks_in_range(centre: &I32Vec3, distance: usize) -> HashSet<I32Vec3> {
+    let radius = distance as i32;
+    let mut positions = HashSet::new();
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            for z in -radius..=radius {
+                let offset = vec3(x, y, z);
+                if distance_squared(&offset, &I32Vec3::zeros()) <= radius * radius {
+                    positions.insert(centre + offset);
+                }
+            }
+        }
+    }
+    positions
+}
+
+/// Splits a world space block position into its chunk position and the position inside that chunk
+pub fn split_block_position(position: &I32Vec3) -> (I32Vec3, [u32; 3]) {
+    let chunk = position.map(|p| p.div_euclid(CHUNK_SIZE));
+    let local = position.map(|p| p.rem_euclid(CHUNK_SIZE) as u32);
+    (chunk, [local.x, local.y, local.z])
+}
+
+/// The chunk holding a world space block position and any chunk sharing a face, edge or corner with that block,
+/// which are the chunks whose padding includes it
+pub fn chunks_touching(position: &I32Vec3) -> Vec<I32Vec3> {
+    let (chunk, local) = split_block_position(position);
+    let mut touching = vec![chunk];
+    for offset in neighbour_offsets() {
+        let on_border = (0..3).all(|axis| match offset[axis] {
+            -1 => local[axis] == 0,
+            0 => true,
+            _ => local[axis] == CHUNK_SIZE as u32 - 1,
+        });
+        if on_border {
+            touching.push(chunk + I32Vec3::from(offset));
+        }
+    }
+    touching
+}
+
+fn distance_squared(a: &I32Vec3, b: &I32Vec3) -> i32 {
+    let difference = a - b;
+    difference.dot(&difference)
+}
+
+/// The world space box a chunk occupies
+fn chunk_bounds(position: &I32Vec3) -> Aabb {
+    let min = position * CHUNK_SIZE;
+    let max = min.add_scalar(CHUNK_SIZE);
+    Aabb::new(
+        Point3::new(min.x as f32, min.y as f32, min.z as f32),
+        Point3::new(max.x as f32, max.y as f32, max.z as f32)
+    )
+}
@@ -1,5 +1,7 @@
 mod game;
 mod engine;
+#[cfg(test)]
+mod tests;
 
 extern crate nalgebra_glm as glm;
 extern crate glfw;
@@ -7,32 +9,30 @@ extern crate gl;
 extern crate lazy_static;
 extern crate image;
 
-use crate::engine::generation::*;
 use std::{mem::size_of, path::Path, ffi::c_void};
-use cgmath::{Matrix4, vec3, Rad, perspective, Deg, InnerSpace, Point3};
+use cgmath::{Matrix4, vec3, perspective, Deg, Point3};
 
-use engine::shader::Shader;
+use engine::shader::{Shader, ShaderSources};
 use engine::util::*;
-use game::chunk::Chunk;
-use gl::{types::*, ARRAY_BUFFER, TRIANGLES};
+use game::{world::World, block::{BlockRegistry, texture_path}, chunk_vertex::ChunkVertex, sun::Sun, CHUNK_SIZE};
 use glfw::Key;
 
 use engine::{
+    buffer_object::{VertexArrayObject, VertexBufferObject},
+    texture_array::TextureLayers,
+    uniform_buffer::{FrameData, FrameDataBuffer},
     keybinds::*,
     input_functions::*,
     camera::Camera,
+    frustum::Frustum,
     window::Window
 };
 use glm::vec3 as glm_vec3;
-use noise::{Perlin, Fbm, Seedable};
 
 fn main() {
     let scr_width: u32 = 1280;
     let scr_height: u32 = 720;
 
-    let img = image::open(&Path::new("dirt.png")).unwrap().to_rgba8();
-    let data = img.to_vec();
-
     let mut camera = Camera {
         position: Point3::new(0.0, 1.0, 0.0),
         ..Default::default()
@@ -82,7 +82,9 @@ fn main() {
         KeyBinding::new(Key::RightShift, false, toggle_cursor_mode),
         KeyBinding::new(Key::RightControl, false, print_camera_pos),
         KeyBinding::new(Key::LeftControl, false, increase_movement_speed),
-        KeyBinding::new(Key::F11, false, toggle_fullscreen)
+        KeyBinding::new(Key::F11, false, toggle_fullscreen),
+        KeyBinding::new(Key::F3, false, toggle_meshing_mode),
+        KeyBinding::new(Key::F4, false, toggle_chunk_meshing_mode)
     ];
 
     //let vertices: Vec<f32> = vec![
@@ -172,42 +174,23 @@ fn main() {
         0.0, 1.0, 0.0, 0.0, 1.0
    ];
 
-    let world = World::new(4, 1);
-    let noise_fn = Fbm::<Perlin>::default().set_seed(1 as u32);
-
-    let test_chunk = Chunk::gen(glm_vec3(0, 0, 0), &noise_fn);
+    let registry = BlockRegistry::load("blocks.ron").unwrap_or_else(|err| panic!("Failed to load blocks.ron: {}", err));
+    let block_textures = TextureLayers::load(registry.texture_names().iter().map(|name| texture_path(Path::new("."), name)))
+        .unwrap_or_else(|err| panic!("Failed to load block textures: {}", err))
+        .upload();
+    let mut world = World::default().set_seed(1).set_registry(registry);
+    let render_distance = 4;
+    world.set_render_distance(render_distance);
+    world.set_generation_budget(2);
 
-    let mut vbo: GLuint = 0;
-    let mut vao: GLuint = 0;
-    //let mut ebo: GLuint = 0;
-    let mut texture: GLuint = 0;
+    let vao = VertexArrayObject::new();
+    let mut vbo = VertexBufferObject::new();
 
     unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::GenBuffers(1,  &mut vbo);
-        //gl::GenBuffers(1,  &mut ebo);
-        gl::BindVertexArray(vao);
+        vao.bind();
+        vbo.buffer_data(&vertices, gl::STATIC_DRAW);
     }
 
-    unsafe {
-        gl::BindBuffer(ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (vertices.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr,
-            vertices.as_ptr().cast(),
-            gl::STATIC_DRAW,
-        );
-    }
-
-    //unsafe {
-    //    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-    //    gl::BufferData(
-    //        gl::ELEMENT_ARRAY_BUFFER,
-    //        size_of_val(&indices) as GLsizeiptr,
-    //        indices.as_ptr().cast(),
-    //        gl::STATIC_DRAW
-    //    )
-    //}
     unsafe {
         gl::EnableVertexAttribArray(0);
         gl::VertexAttribPointer(
@@ -229,47 +212,25 @@ fn main() {
             (3 * size_of::<f32>()) as *const c_void
         );
 
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
+        vbo.unbind();
+        vao.unbind();
     }
-    let shader_program = Shader::new("triangle.vert", "triangle.frag");
-
-    unsafe {
-        gl::GenTextures(1, &mut texture);
-    }
-
-    unsafe  {
-        gl::BindTexture(gl::TEXTURE_2D, texture);
-    }
-
-    unsafe {
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    // a broken shader is reported and drawn with nothing until it is fixed, rather than taking the game down
+    let mut shader_program = Shader::unlinked(ShaderSources::new("chunk.vert", "chunk.frag"));
+    if let Err(err) = shader_program.reload() {
+        eprintln!("{}", err);
     }
-
-    unsafe {
-        gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGBA as i32,
-            img.width() as i32,
-            img.height() as i32,
-            0,
-            gl::RGBA,
-            gl::UNSIGNED_BYTE,
-            &data[0] as *const u8 as *const std::ffi::c_void
-        );
-        gl::GenerateMipmap(gl::TEXTURE_2D)
+    if let Err(err) = shader_program.check_vertex_layout::<ChunkVertex>() {
+        eprintln!("{}", err);
     }
-
-
     unsafe {
-        //gl::BindVertexArray(vao);
-        //gl::BindTexture(gl::TEXTURE_2D, texture);
+        shader_program.use_program();
+        shader_program.set_uniform("blockTextures", 0);
     }
+    let mut frame_data = unsafe { FrameDataBuffer::new() };
+    // fog fades chunks into the clear colour before they reach the edge of the render distance
+    let fog_end = (render_distance as i32 * CHUNK_SIZE) as f32;
+    let sun = Sun::default();
 
     while !window.should_close() {
 
@@ -279,7 +240,7 @@ fn main() {
 
         window.process_events(&mut first_mouse, &mut last_x, &mut last_y, &mut camera);
 
-        process_input(&mut window, &delta_time, &mut keybindings, &mut camera);
+        process_input(&mut window, &delta_time, &mut keybindings, &mut camera, &mut world);
 
 
         unsafe {
@@ -287,31 +248,62 @@ fn main() {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
+        world.update(&glm_vec3(camera.position.x, camera.position.y, camera.position.z));
+
+        match shader_program.reload_if_changed() {
+            Ok(true) => unsafe {
+                let sources = shader_program.sources();
+                println!("Reloaded {} and {}", sources.vertex_path.display(), sources.fragment_path.display());
+                if let Err(err) = shader_program.check_vertex_layout::<ChunkVertex>() {
+                    eprintln!("{}", err);
+                }
+                shader_program.use_program();
+                shader_program.set_uniform("blockTextures", 0);
+            },
+            Ok(false) => {},
+            Err(err) => eprintln!("{}", err)
+        }
+
         let (width, height) = window.get_framebuffer_size();
 
-        let model: Matrix4<f32> = Matrix4::from_axis_angle(
-            vec3(0.5, 1.0, 0.0).normalize(),
-            Rad(window.context.get_time() as f32)
-        );
         let view = camera.get_view_matrix();
         let projection: Matrix4<f32> = perspective(Deg(camera.zoom), width as f32 / height as f32, 0.1, 100.0);
 
         unsafe {
-            shader_program.set_mat4("model", &model);
-            shader_program.set_mat4("view", &view);
-            shader_program.set_mat4("projection", &projection);
-            shader_program.set_vec4("ourColor", 1.0, 1.0, 1.0, 0.0);
+            frame_data.update(&FrameData {
+                view,
+                projection,
+                camera_position: camera.position,
+                time: current_frame,
+                fog_colour: vec3(128.0, 128.0, 128.0) / 255.0,
+                fog_start: fog_end * 0.75,
+                fog_end,
+                sun_direction: sun.direction,
+                sun_colour: sun.colour,
+                ambient: sun.ambient,
+            });
+            shader_program.use_program();
         }
         unsafe {
-            shader_program.use_program();
+            block_textures.bind_to_unit(0);
             //gl::DrawArrays(TRIANGLES, 0, 3 as GLsizei);
-            test_chunk.mesh.draw(&shader_program);
+            let frustum = Frustum::from_matrix(projection * view);
+            let camera_position = glm_vec3(camera.position.x, camera.position.y, camera.position.z);
+            for chunk in world.reachable_chunks(&camera_position, &frustum) {
+                let Some(mesh) = &chunk.mesh else {
+                    continue;
+                };
+                let offset = chunk.position * CHUNK_SIZE;
+                let model: Matrix4<f32> = Matrix4::from_translation(vec3(offset.x as f32, offset.y as f32, offset.z as f32));
+                shader_program.set_uniform("model", model);
+                mesh.draw(&shader_program);
+            }
             //for chunk_vec in &world.chunks {
             //    for chunk in chunk_vec {
             //        for (pos, block) in chunk.blocks.iter() {
             //            if block.1 {
             //                let model: Matrix4<f32> = Matrix4::from_translation(block_pos_to_f32(pos + (chunk.position * 16)));
-            //                shader_program.set_mat4("model", &model);
+            //                shader_program.set_uniform("model", &model);
             //                gl::DrawArrays(TRIANGLES, 0, (vertices.len() / 3) as i32)
             //            }
             //        }
@@ -359,10 +351,10 @@ fn main() {
 //    }
 //}
 
-fn process_input(window: &mut Window, delta_time: &f32, bindings: &mut [KeyBinding], camera: &mut Camera) {
+fn process_input(window: &mut Window, delta_time: &f32, bindings: &mut [KeyBinding], camera: &mut Camera, world: &mut World) {
     for binding in bindings.iter_mut() {
         let action = window.get_key(binding.key);
-        binding.update(action, InputFunctionArguments::new().camera(camera).window(window).delta_time(delta_time).action(&action))
+        binding.update(action, InputFunctionArguments::new().camera(camera).window(window).delta_time(delta_time).action(&action).world(world))
     }
 }
 
@@ -0,0 +1,126 @@
+use block_mesh::ndshape::ConstShape;
+use glm::Vec3;
+
+use crate::game::block::{Block, BlockFace, BlockRegistry};
+use crate::game::chunk::{pad_blocks, Blocks, Chunk, ChunkShape, MeshingMode, PaddedChunkShape};
+use crate::game::chunk_vertex::ChunkVertex;
+
+use super::{empty, full, stone, ChunkMesh};
+
+/// A one block thick stone floor at `y = 0` with stones placed on top of it at `blocks_on_top`
+fn floor_with(blocks_on_top: &[[u32; 3]]) -> Blocks {
+    let mut blocks = empty();
+    for x in 0..16 {
+        for z in 0..16 {
+            blocks[ChunkShape::linearize([x, 0, z]) as usize] = stone();
+        }
+    }
+    for position in blocks_on_top {
+        blocks[ChunkShape::linearize(*position) as usize] = stone();
+    }
+    blocks
+}
+
+fn mesh(blocks: &Blocks, mode: MeshingMode) -> ChunkMesh {
+    Chunk::mesh_blocks(blocks, &BlockRegistry::default(), mode)
+}
+
+/// Occlusion of every top face vertex of the floor at `position`
+fn floor_occlusion(mesh: &ChunkMesh, position: [u32; 3]) -> Vec<u8> {
+    mesh.vertices.iter()
+        .filter(|vertex| vertex.face() == BlockFace::Top && vertex.position() == position)
+        .map(ChunkVertex::occlusion)
+        .collect()
+}
+
+fn top_quad_count(mesh: &ChunkMesh) -> usize {
+    mesh.vertices.iter().filter(|vertex| vertex.face() == BlockFace::Top && vertex.position()[1] == 1).count() / 4
+}
+
+#[test]
+fn vertex_occlusion_round_trips_without_touching_other_fields() {
+    let vertex = ChunkVertex::new([16, 3, 9], BlockFace::West, [4, 16], 300).set_occlusion(1);
+    assert_eq!(vertex.occlusion(), 1);
+    assert_eq!(vertex.position(), [16, 3, 9]);
+    assert_eq!(vertex.face(), BlockFace::West);
+    assert_eq!(vertex.set_occlusion(3).occlusion(), 3);
+}
+
+#[test]
+fn an_isolated_block_is_unoccluded() {
+    let mut blocks = empty();
+    blocks[ChunkShape::linearize([8, 8, 8]) as usize] = stone();
+    let mesh = mesh(&blocks, MeshingMode::Greedy);
+    assert_eq!(mesh.vertices.len(), 24);
+    assert!(mesh.vertices.iter().all(|vertex| vertex.occlusion() == 3));
+}
+
+#[test]
+fn a_block_darkens_the_floor_corners_around_it() {
+    let mesh = mesh(&floor_with(&[[8, 1, 8]]), MeshingMode::Simple);
+    // the three floor faces meeting at a corner of the block each have one solid side or diagonal
+    assert_eq!(floor_occlusion(&mesh, [9, 1, 8]), vec![2; 3]);
+    assert_eq!(floor_occlusion(&mesh, [10, 1, 8]), vec![3; 4]);
+    assert_eq!(floor_occlusion(&mesh, [0, 1, 0]), vec![3]);
+}
+
+#[test]
+fn corners_boxed_in_on_both_sides_are_fully_occluded() {
+    let mesh = mesh(&floor_with(&[[8, 1, 9], [9, 1, 8]]), MeshingMode::Simple);
+    assert_eq!(floor_occlusion(&mesh, [9, 1, 9]), vec![0; 2]);
+}
+
+#[test]
+fn quads_are_split_along_the_brighter_diagonal() {
+    for mode in [MeshingMode::Simple, MeshingMode::Greedy] {
+        let mesh = mesh(&floor_with(&[[8, 1, 9], [9, 1, 8], [3, 1, 3]]), mode);
+        let mut uneven = false;
+        for (quad, indices) in mesh.indices.chunks(6).enumerate() {
+            let (first, second) = indices.split_at(3);
+            // the two vertices both triangles share make up the diagonal
+            let (diagonal, others): (Vec<u32>, Vec<u32>) = (0..4).map(|i| quad as u32 * 4 + i).partition(|i| first.contains(i) && second.contains(i));
+            let sum = |vertices: &[u32]| vertices.iter().map(|i| mesh.vertices[*i as usize].occlusion()).sum::<u8>();
+            assert_eq!(diagonal.len(), 2);
+            assert!(sum(&diagonal) >= sum(&others), "quad {} is split along its darker diagonal", quad);
+            uneven |= sum(&diagonal) != sum(&others);
+        }
+        assert!(uneven, "every quad is shaded evenly");
+
+        // swapping the diagonal keeps the winding
+        for triangle in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.vertices[triangle[i] as usize].position().map(|p| p as f32)));
+            let normal = Vec3::from(mesh.vertices[triangle[0] as usize].face().normal().map(|n| n as f32));
+            assert!((b - a).cross(&(c - a)).dot(&normal) > 0.0, "triangle {:?} is wound against its normal", triangle);
+        }
+    }
+}
+
+#[test]
+fn differently_occluded_faces_are_not_merged() {
+    assert_eq!(top_quad_count(&mesh(&floor_with(&[]), MeshingMode::Greedy)), 1);
+
+    let blocks = floor_with(&[[8, 1, 8]]);
+    let greedy = mesh(&blocks, MeshingMode::Greedy);
+    assert!(top_quad_count(&greedy) > 4);
+    // every corner shows the same shading as meshing face by face would give it
+    let simple = mesh(&blocks, MeshingMode::Simple);
+    for vertex in greedy.vertices.iter().filter(|vertex| vertex.face() == BlockFace::Top && vertex.position()[1] == 1) {
+        let expected = floor_occlusion(&simple, vertex.position());
+        assert!(expected.contains(&vertex.occlusion()), "{:?} has occlusion {}, expected one of {:?}", vertex.position(), vertex.occlusion(), expected);
+    }
+}
+
+#[test]
+fn padding_includes_edge_and_corner_neighbours() {
+    let solid = full();
+    let padded = pad_blocks(&empty(), |offset| match offset {
+        [1, 1, 0] | [-1, -1, -1] => Some(&solid),
+        _ => None,
+    });
+    for z in 1..17 {
+        assert_eq!(padded[PaddedChunkShape::linearize([17, 17, z]) as usize], stone());
+    }
+    assert_eq!(padded[PaddedChunkShape::linearize([0, 0, 0]) as usize], stone());
+    let solid_count = padded.iter().filter(|block| **block != Block::AIR).count();
+    assert_eq!(solid_count, 17);
+}
@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+
+use crate::game::block::{BlockDefinitionError, BlockFace, BlockRegistry, BlockRegistryError, Opacity};
+
+fn texture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn load(source: &str) -> Result<BlockRegistry, BlockDefinitionError> {
+    BlockRegistry::from_definitions(source, &texture_dir())
+}
+
+#[test]
+fn the_shipped_definitions_load() {
+    let registry = BlockRegistry::load(texture_dir().join("blocks.ron")).unwrap();
+    let grass = registry.get(registry.block("grass").unwrap());
+    assert_eq!(grass.textures.get(BlockFace::Top), "grass_top");
+    assert_eq!(grass.textures.get(BlockFace::Bottom), "dirt");
+    assert_eq!(grass.textures.get(BlockFace::North), "grass_side");
+    assert!(registry.block("stone").is_some());
+}
+
+#[test]
+fn specific_faces_override_sides_and_all() {
+    let registry = load(r#"[
+        (name: "log", textures: (all: "dirt", sides: "stone", north: "grass_side"), transparency: Translucent, collision: false),
+    ]"#).unwrap();
+    let log = registry.get(registry.block("log").unwrap());
+    assert_eq!(log.textures.get(BlockFace::Top), "dirt");
+    assert_eq!(log.textures.get(BlockFace::East), "stone");
+    assert_eq!(log.textures.get(BlockFace::North), "grass_side");
+    assert_eq!(log.opacity, Opacity::Translucent);
+    assert!(!log.solid);
+    assert_eq!(log.light_emission, 0);
+    assert_eq!(log.hardness, 1.0);
+}
+
+#[test]
+fn duplicate_names_are_rejected() {
+    let result = load(r#"[
+        (name: "stone", textures: (all: "stone"), transparency: Opaque, collision: true),
+        (name: "stone", textures: (all: "dirt"), transparency: Opaque, collision: true),
+    ]"#);
+    assert!(matches!(result, Err(BlockDefinitionError::Registry(BlockRegistryError::DuplicateName(name))) if name == "stone"));
+}
+
+#[test]
+fn light_emission_above_the_brightest_light_is_rejected() {
+    let result = load(r#"[
+        (name: "lamp", textures: (all: "stone"), transparency: Opaque, collision: true, light_emission: 15),
+        (name: "sun", textures: (all: "stone"), transparency: Opaque, collision: true, light_emission: 16),
+    ]"#);
+    let Err(err @ BlockDefinitionError::Registry(BlockRegistryError::LightEmissionTooHigh { .. })) = result else {
+        panic!("expected the emission to be rejected, got {:?}", result);
+    };
+    assert!(err.to_string().contains("\"sun\" emits light level 16"), "{}", err);
+}
+
+#[test]
+fn unknown_properties_are_rejected_with_their_position() {
+    let result = load(r#"[
+        (name: "stone", textures: (all: "stone"), transparency: Opaque, collision: true, bounciness: 2.0),
+    ]"#);
+    let Err(err @ BlockDefinitionError::Parse { line, .. }) = result else {
+        panic!("expected a parse error, got {:?}", result);
+    };
+    assert_eq!(line, 2);
+    assert!(err.to_string().contains("bounciness"), "{}", err);
+}
+
+#[test]
+fn faces_without_a_texture_are_rejected() {
+    let result = load(r#"[
+        (name: "grass", textures: (top: "grass_top", sides: "grass_side"), transparency: Opaque, collision: true),
+    ]"#);
+    assert!(matches!(result, Err(BlockDefinitionError::MissingTexture { face: BlockFace::Bottom, .. })));
+}
+
+#[test]
+fn textures_must_exist() {
+    let result = load(r#"[
+        (name: "glass", textures: (all: "glass"), transparency: Translucent, collision: true),
+    ]"#);
+    let Err(BlockDefinitionError::TextureNotFound { block, path, .. }) = result else {
+        panic!("expected a missing texture file, got {:?}", result);
+    };
+    assert_eq!(block, "glass");
+    assert_eq!(path, texture_dir().join("glass.png"));
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let result = BlockRegistry::load(Path::new("does_not_exist.ron"));
+    assert!(matches!(result, Err(BlockDefinitionError::Io { .. })));
+}
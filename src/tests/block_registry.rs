@@ -0,0 +1,63 @@
+use crate::game::block::{Block, BlockFace, BlockProperties, BlockRegistry, BlockRegistryError, BlockTextures, Opacity};
+use crate::game::chunk::{Chunk, ChunkShape, MeshingMode};
+
+use super::empty;
+use block_mesh::ndshape::ConstShape;
+
+fn glass() -> BlockProperties {
+    BlockProperties {
+        name: String::from("glass"),
+        opacity: Opacity::Translucent,
+        solid: true,
+        textures: BlockTextures::all("glass"),
+        light_emission: 0,
+        hardness: 0.3,
+    }
+}
+
+#[test]
+fn air_is_always_the_first_block() {
+    let mut registry = BlockRegistry::new();
+    assert_eq!(registry.block("air"), Some(Block::AIR));
+    assert!(!registry.get(Block::AIR).is_visible());
+    // air is the only block a new registry starts with, so the next one takes the id after it
+    assert_eq!(registry.register(glass()), Ok(Block(Block::AIR.0 + 1)));
+}
+
+#[test]
+fn duplicate_names_are_rejected() {
+    let mut registry = BlockRegistry::default();
+    let glass_block = registry.register(glass()).unwrap();
+    assert_eq!(registry.block("glass"), Some(glass_block));
+    assert_eq!(registry.register(glass()), Err(BlockRegistryError::DuplicateName(String::from("glass"))));
+}
+
+#[test]
+fn unknown_ids_are_treated_as_air() {
+    let registry = BlockRegistry::default();
+    assert_eq!(registry.get(Block(999)).name, "air");
+}
+
+#[test]
+fn per_face_textures() {
+    let registry = BlockRegistry::default();
+    let grass = registry.get(registry.block("grass").unwrap());
+    assert_eq!(grass.textures.get(BlockFace::Top), "grass_top");
+    assert_eq!(grass.textures.get(BlockFace::Bottom), "dirt");
+    assert_eq!(grass.textures.get(BlockFace::East), "grass_side");
+}
+
+#[test]
+fn opaque_faces_behind_translucent_blocks_are_meshed() {
+    let mut registry = BlockRegistry::default();
+    let glass_block = registry.register(glass()).unwrap();
+    let stone = registry.block("stone").unwrap();
+
+    let mut blocks = empty();
+    blocks[ChunkShape::linearize([4, 4, 4]) as usize] = stone;
+    blocks[ChunkShape::linearize([5, 4, 4]) as usize] = glass_block;
+
+    // the stone face touching the glass is still visible, the glass face touching the stone is hidden
+    let mesh = Chunk::mesh_blocks(&blocks, &registry, MeshingMode::Greedy);
+    assert_eq!(mesh.vertices.len() / 4, 11);
+}
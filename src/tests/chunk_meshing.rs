@@ -0,0 +1,215 @@
+use block_mesh::ndshape::ConstShape;
+use glm::Vec3;
+
+use crate::game::block::{Block, BlockFace, BlockRegistry};
+use crate::game::chunk::{Chunk, ChunkShape, PaddedChunkShape, Blocks, PaddedBlocks, MeshingMode, pad_blocks};
+use crate::game::chunk_vertex::ChunkVertex;
+use crate::game::light::LightLevel;
+
+use super::{empty, full, stone, ChunkMesh};
+
+fn mesh(blocks: &Blocks) -> ChunkMesh {
+    Chunk::mesh_blocks(blocks, &BlockRegistry::default(), MeshingMode::Greedy)
+}
+
+fn mesh_simple(blocks: &Blocks) -> ChunkMesh {
+    Chunk::mesh_blocks(blocks, &BlockRegistry::default(), MeshingMode::Simple)
+}
+
+fn mesh_padded(padded: &PaddedBlocks) -> ChunkMesh {
+    Chunk::mesh_padded(padded, &[LightLevel::SKY; PaddedChunkShape::SIZE as usize], &BlockRegistry::default(), MeshingMode::Greedy)
+}
+
+fn single_block(x: u32, y: u32, z: u32) -> Blocks {
+    let mut blocks = empty();
+    blocks[ChunkShape::linearize([x, y, z]) as usize] = stone();
+    blocks
+}
+
+fn checkerboard() -> Blocks {
+    let mut blocks = empty();
+    for i in 0..ChunkShape::SIZE {
+        let [x, y, z] = ChunkShape::delinearize(i);
+        if (x + y + z) % 2 == 0 {
+            blocks[i as usize] = stone();
+        }
+    }
+    blocks
+}
+
+fn assert_quad_counts(mesh: &ChunkMesh, quads: usize) {
+    assert_eq!(mesh.vertices.len(), quads * 4, "vertex count");
+    assert_eq!(mesh.indices.len(), quads * 6, "index count");
+}
+
+/// Every index is in range and each quad's six indices only use that quad's four vertices
+fn assert_indices_in_bounds(mesh: &ChunkMesh) {
+    for (quad, indices) in mesh.indices.chunks(6).enumerate() {
+        let first = quad as u32 * 4;
+        for index in indices {
+            assert!((*index as usize) < mesh.vertices.len(), "index {} out of bounds", index);
+            assert!((first..first + 4).contains(index), "quad {} uses vertex {} from another quad", quad, index);
+        }
+    }
+}
+
+/// Normals are unit axes, shared by the whole quad, and match the triangle winding
+fn assert_normals_consistent(mesh: &ChunkMesh) {
+    for (quad, vertices) in mesh.vertices.chunks(4).enumerate() {
+        let face = vertices[0].face();
+        assert!(vertices.iter().all(|v| v.face() == face), "quad {} has mixed faces", quad);
+    }
+    for triangle in mesh.indices.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.vertices[triangle[i] as usize].position().map(|p| p as f32)));
+        let normal = Vec3::from(mesh.vertices[triangle[0] as usize].face().normal().map(|n| n as f32));
+        let winding: Vec3 = (b - a).cross(&(c - a));
+        assert!(winding.dot(&normal) > 0.0, "triangle {:?} is wound against its normal", triangle);
+    }
+}
+
+fn assert_inside_chunk(mesh: &ChunkMesh) {
+    for vertex in &mesh.vertices {
+        assert!(vertex.position().iter().all(|p| *p <= 16), "vertex {:?} outside of the chunk", vertex.position());
+    }
+}
+
+#[test]
+fn empty_chunk_has_no_geometry() {
+    let mesh = mesh(&empty());
+    assert_quad_counts(&mesh, 0);
+}
+
+#[test]
+fn single_block_emits_six_faces() {
+    let mesh = mesh(&single_block(8, 8, 8));
+    assert_quad_counts(&mesh, 6);
+    assert_indices_in_bounds(&mesh);
+    assert_normals_consistent(&mesh);
+
+    let mut normals: Vec<[i32; 3]> = mesh.vertices.chunks(4)
+        .map(|quad| quad[0].face().normal())
+        .collect();
+    normals.sort();
+    assert_eq!(normals, vec![[-1, 0, 0], [0, -1, 0], [0, 0, -1], [0, 0, 1], [0, 1, 0], [1, 0, 0]]);
+
+    for vertex in &mesh.vertices {
+        assert!(vertex.position().iter().all(|p| *p == 8 || *p == 9), "vertex {:?} not on the block", vertex.position());
+    }
+}
+
+#[test]
+fn single_block_on_the_border_is_meshed() {
+    let mesh = mesh(&single_block(0, 15, 0));
+    assert_quad_counts(&mesh, 6);
+    assert_inside_chunk(&mesh);
+}
+
+#[test]
+fn full_chunk_merges_into_one_quad_per_side() {
+    let mesh = mesh(&full());
+    assert_quad_counts(&mesh, 6);
+    assert_indices_in_bounds(&mesh);
+    assert_normals_consistent(&mesh);
+    assert_inside_chunk(&mesh);
+}
+
+#[test]
+fn checkerboard_exposes_every_face() {
+    let mesh = mesh(&checkerboard());
+    // no two solid blocks share a face or sit next to each other in a plane, so nothing merges
+    assert_quad_counts(&mesh, (ChunkShape::SIZE as usize / 2) * 6);
+    assert_indices_in_bounds(&mesh);
+    assert_normals_consistent(&mesh);
+    assert_inside_chunk(&mesh);
+}
+
+#[test]
+fn padding_takes_the_touching_layer_of_each_neighbour() {
+    let below = single_block(3, 15, 4);
+    let beside = single_block(0, 7, 9);
+    let padded = pad_blocks(&empty(), |offset| match offset {
+        [1, 0, 0] => Some(&beside),
+        [0, -1, 0] => Some(&below),
+        _ => None,
+    });
+
+    assert_eq!(padded[PaddedChunkShape::linearize([4, 0, 5]) as usize], stone());
+    assert_eq!(padded[PaddedChunkShape::linearize([17, 8, 10]) as usize], stone());
+    let solid = padded.iter().filter(|block| **block != Block::AIR).count();
+    assert_eq!(solid, 2);
+}
+
+#[test]
+fn border_faces_against_solid_neighbours_are_culled() {
+    let solid = full();
+    let padded = pad_blocks(&full(), |offset| (offset == [1, 0, 0]).then_some(&solid));
+    let mesh = mesh_padded(&padded);
+    assert_quad_counts(&mesh, 5);
+    assert!(mesh.vertices.iter().all(|vertex| vertex.face() != BlockFace::East), "face against the +x neighbour was emitted");
+}
+
+#[test]
+fn chunk_enclosed_by_solid_neighbours_has_no_geometry() {
+    let solid = full();
+    let padded = pad_blocks(&full(), |_| Some(&solid));
+    assert_quad_counts(&mesh_padded(&padded), 0);
+}
+
+#[test]
+fn border_faces_against_empty_neighbours_are_kept() {
+    let air = empty();
+    let padded = pad_blocks(&full(), |_| Some(&air));
+    assert_quad_counts(&mesh_padded(&padded), 6);
+}
+
+#[test]
+fn only_faces_of_the_same_block_are_merged() {
+    let registry = BlockRegistry::default();
+    let dirt = registry.block("dirt").unwrap();
+
+    let mut blocks = single_block(4, 4, 4);
+    blocks[ChunkShape::linearize([5, 4, 4]) as usize] = stone();
+    // two stones in a row share their four long faces
+    assert_quad_counts(&mesh(&blocks), 6);
+
+    blocks[ChunkShape::linearize([5, 4, 4]) as usize] = dirt;
+    // stone and dirt are kept apart, each showing five faces
+    let mesh = mesh(&blocks);
+    assert_quad_counts(&mesh, 10);
+    assert_indices_in_bounds(&mesh);
+    assert_normals_consistent(&mesh);
+}
+
+#[test]
+fn simple_meshing_emits_one_quad_per_visible_face() {
+    let mesh = mesh_simple(&full());
+    assert_quad_counts(&mesh, 16 * 16 * 6);
+    assert_indices_in_bounds(&mesh);
+    assert_normals_consistent(&mesh);
+    assert_inside_chunk(&mesh);
+
+    assert_quad_counts(&mesh_simple(&single_block(0, 15, 0)), 6);
+    assert_quad_counts(&mesh_simple(&checkerboard()), (ChunkShape::SIZE as usize / 2) * 6);
+}
+
+#[test]
+fn chunk_vertex_round_trips_its_fields() {
+    let vertex = ChunkVertex::new([16, 0, 7], BlockFace::South, [16, 3], 1234);
+    assert_eq!(vertex.position(), [16, 0, 7]);
+    assert_eq!(vertex.face(), BlockFace::South);
+    assert_eq!(vertex.uv(), [16, 3]);
+    assert_eq!(vertex.layer(), 1234);
+    assert_eq!(std::mem::size_of::<ChunkVertex>(), 8);
+}
+
+#[test]
+fn greedy_quads_tile_their_texture_across_the_merged_area() {
+    let mesh = mesh(&full());
+    let registry = BlockRegistry::default();
+    for quad in mesh.vertices.chunks(4) {
+        let mut uvs: Vec<[u32; 2]> = quad.iter().map(ChunkVertex::uv).collect();
+        uvs.sort();
+        assert_eq!(uvs, vec![[0, 0], [0, 16], [16, 0], [16, 16]]);
+        assert!(quad.iter().all(|v| v.layer() == registry.texture_layer(stone(), v.face())));
+    }
+}
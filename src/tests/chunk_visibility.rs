@@ -0,0 +1,112 @@
+use block_mesh::ndshape::ConstShape;
+use cgmath::{perspective, Deg, Matrix4, Point3, Vector3};
+use glm::{vec3, I32Vec3};
+
+use crate::engine::frustum::Frustum;
+use crate::game::block::{Block, BlockFace, BlockRegistry};
+use crate::game::chunk::{pad_blocks, Blocks, Chunk, ChunkShape};
+use crate::game::visibility::FaceConnectivity;
+use crate::game::world::World;
+
+use super::{empty, full};
+
+fn carve(blocks: &mut Blocks, positions: impl IntoIterator<Item = [u32; 3]>) {
+    for position in positions {
+        blocks[ChunkShape::linearize(position) as usize] = Block::AIR;
+    }
+}
+
+/// Solid rock with a straight tunnel running the length of the z axis
+fn z_tunnel() -> Blocks {
+    let mut blocks = full();
+    carve(&mut blocks, (0..16).map(|z| [8, 8, z]));
+    blocks
+}
+
+/// Connectivity of `blocks` on their own, with no neighbours loaded around them
+fn connectivity(blocks: &Blocks) -> FaceConnectivity {
+    FaceConnectivity::from_padded(&pad_blocks(blocks, |_| None), &BlockRegistry::default())
+}
+
+#[test]
+fn empty_chunk_connects_every_face() {
+    assert_eq!(connectivity(&empty()), FaceConnectivity::ALL);
+}
+
+#[test]
+fn solid_chunk_connects_nothing() {
+    assert_eq!(connectivity(&full()), FaceConnectivity::NONE);
+}
+
+#[test]
+fn tunnel_only_connects_its_two_ends() {
+    let connectivity = connectivity(&z_tunnel());
+    assert!(connectivity.connects(BlockFace::North, BlockFace::South));
+    assert!(connectivity.connects(BlockFace::South, BlockFace::North));
+    assert!(!connectivity.connects(BlockFace::North, BlockFace::Top));
+    assert!(!connectivity.connects(BlockFace::West, BlockFace::East));
+}
+
+#[test]
+fn separate_pockets_stay_apart() {
+    let mut blocks = full();
+    // one pocket bending from the west face up to the top, another opening onto the south face only
+    carve(&mut blocks, (0..4).map(|x| [x, 4, 4]).chain((4..16).map(|y| [3, y, 4])));
+    carve(&mut blocks, (10..16).map(|z| [12, 12, z]));
+    let connectivity = connectivity(&blocks);
+    assert!(connectivity.connects(BlockFace::West, BlockFace::Top));
+    assert!(!connectivity.connects(BlockFace::West, BlockFace::South));
+    assert!(!connectivity.connects(BlockFace::Top, BlockFace::South));
+    assert!(!connectivity.connects(BlockFace::South, BlockFace::North));
+}
+
+/// A camera in the middle of chunk (0, 0, 0) looking down -z
+fn frustum() -> Frustum {
+    let view = Matrix4::look_at_rh(Point3::new(8.0, 8.0, 8.0), Point3::new(8.0, 8.0, 0.0), Vector3::unit_y());
+    Frustum::from_matrix(perspective(Deg(90.0), 1.0, 0.1, 200.0) * view)
+}
+
+/// A column of chunks running away from the camera, the camera's own chunk being air
+fn column(middle: Blocks) -> World {
+    let mut world = World::default();
+    for (z, blocks) in [(0, empty()), (-1, middle), (-2, empty())] {
+        let mut chunk = Chunk::new(vec3(0, 0, z), blocks);
+        chunk.connectivity = connectivity(&chunk.blocks);
+        world.insert_chunk(chunk);
+    }
+    world
+}
+
+fn reachable(world: &World) -> Vec<i32> {
+    let mut positions: Vec<i32> = world.reachable_chunks(&vec3(8.0, 8.0, 8.0), &frustum()).iter()
+        .map(|chunk| chunk.position.z)
+        .collect();
+    positions.sort();
+    positions
+}
+
+#[test]
+fn chunks_behind_solid_rock_are_skipped() {
+    // the solid chunk itself may be seen, what lies beyond it can't
+    assert_eq!(reachable(&column(full())), vec![-1, 0]);
+}
+
+#[test]
+fn chunks_seen_through_a_tunnel_are_kept() {
+    assert_eq!(reachable(&column(z_tunnel())), vec![-2, -1, 0]);
+}
+
+#[test]
+fn walk_stays_inside_the_frustum() {
+    let mut world = column(z_tunnel());
+    world.insert_chunk(Chunk::new(vec3(0, 0, 1), empty()));
+    // the chunk behind the camera is never drawn
+    assert_eq!(reachable(&world), vec![-2, -1, 0]);
+}
+
+#[test]
+fn unloaded_camera_chunk_falls_back_to_frustum_culling() {
+    let mut world = column(full());
+    world.remove_chunk(&I32Vec3::zeros());
+    assert_eq!(reachable(&world), vec![-2, -1]);
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use block_mesh::ndshape::ConstShape;
+use glm::vec3;
+use noise::{Fbm, Perlin};
+
+use crate::game::block::{Block, BlockRegistry};
+use crate::game::chunk::{MeshingMode, PaddedChunkShape};
+use crate::game::light::LightLevel;
+use crate::game::worker::ChunkWorkerPool;
+
+/// Polls `receive` until it returns something, giving up after a few seconds
+fn wait_for<T>(mut receive: impl FnMut() -> Option<T>) -> T {
+    let start = Instant::now();
+    loop {
+        if let Some(result) = receive() {
+            return result;
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out waiting for a worker");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn pool() -> ChunkWorkerPool {
+    ChunkWorkerPool::new(1, Fbm::<Perlin>::default(), Arc::new(BlockRegistry::default()))
+}
+
+#[test]
+fn generated_chunks_and_meshes_come_back_separately() {
+    let pool = pool();
+    let padded = Box::new([Block::AIR; PaddedChunkShape::SIZE as usize]);
+    let light = Box::new([LightLevel::SKY; PaddedChunkShape::SIZE as usize]);
+    let mesh_token = pool.submit_mesh(vec3(1, 2, 3), padded, light, MeshingMode::Greedy);
+    let generate_token = pool.submit_generate(vec3(-1, 0, 4));
+
+    let meshed = wait_for(|| pool.try_recv_meshed());
+    assert_eq!(meshed.position, vec3(1, 2, 3));
+    assert!(Arc::ptr_eq(&meshed.token, &mesh_token));
+    assert!(meshed.output.mesh.is_empty());
+
+    let generated = wait_for(|| pool.try_recv_generated());
+    assert_eq!(generated.output.position, vec3(-1, 0, 4));
+    assert!(Arc::ptr_eq(&generated.token, &generate_token));
+    assert!(pool.try_recv_meshed().is_none());
+}
@@ -0,0 +1,80 @@
+use cgmath::{perspective, Deg, Matrix4, Point3, Vector3};
+use glm::vec3;
+
+use crate::engine::frustum::{Aabb, Frustum};
+use crate::game::chunk::Chunk;
+use crate::game::world::World;
+
+use super::empty;
+
+/// A camera at the origin looking down -z with a 90 degree field of view, seeing from 0.1 to 100
+fn frustum() -> Frustum {
+    let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0), Vector3::unit_y());
+    Frustum::from_matrix(perspective(Deg(90.0), 1.0, 0.1, 100.0) * view)
+}
+
+fn cube(centre: [f32; 3], half_size: f32) -> Aabb {
+    let [x, y, z] = centre;
+    Aabb::new(
+        Point3::new(x - half_size, y - half_size, z - half_size),
+        Point3::new(x + half_size, y + half_size, z + half_size)
+    )
+}
+
+#[test]
+fn planes_are_normalised() {
+    for plane in frustum().planes {
+        let length = (plane.x * plane.x + plane.y * plane.y + plane.z * plane.z).sqrt();
+        assert!((length - 1.0).abs() < 1e-5, "plane {:?} is not normalised", plane);
+    }
+}
+
+#[test]
+fn boxes_in_front_are_visible() {
+    assert!(frustum().intersects_aabb(&cube([0.0, 0.0, -10.0], 1.0)));
+    assert!(frustum().intersects_aabb(&cube([5.0, -5.0, -20.0], 1.0)));
+}
+
+#[test]
+fn boxes_behind_are_culled() {
+    assert!(!frustum().intersects_aabb(&cube([0.0, 0.0, 10.0], 1.0)));
+    // just behind the near plane
+    assert!(!frustum().intersects_aabb(&cube([0.0, 0.0, 0.5], 0.25)));
+}
+
+#[test]
+fn boxes_beside_are_culled() {
+    // the 90 degree frustum is as wide as it is deep, so these sit outside each side plane
+    assert!(!frustum().intersects_aabb(&cube([-20.0, 0.0, -10.0], 1.0)));
+    assert!(!frustum().intersects_aabb(&cube([20.0, 0.0, -10.0], 1.0)));
+    assert!(!frustum().intersects_aabb(&cube([0.0, 20.0, -10.0], 1.0)));
+    assert!(!frustum().intersects_aabb(&cube([0.0, -20.0, -10.0], 1.0)));
+}
+
+#[test]
+fn boxes_beyond_the_far_plane_are_culled() {
+    assert!(!frustum().intersects_aabb(&cube([0.0, 0.0, -150.0], 10.0)));
+}
+
+#[test]
+fn boxes_straddling_a_plane_are_visible() {
+    // across the left plane, x = z
+    assert!(frustum().intersects_aabb(&cube([-10.0, 0.0, -10.0], 1.0)));
+    // around the camera, across the near plane
+    assert!(frustum().intersects_aabb(&cube([0.0, 0.0, 0.0], 1.0)));
+    // across the far plane
+    assert!(frustum().intersects_aabb(&cube([0.0, 0.0, -100.0], 5.0)));
+}
+
+#[test]
+fn world_only_yields_chunks_in_view() {
+    let mut world = World::default();
+    for position in [vec3(0, 0, -2), vec3(0, 0, 2), vec3(-10, 0, -2), vec3(-1, -1, -1)] {
+        world.insert_chunk(Chunk::new(position, empty()));
+    }
+    let frustum = frustum();
+    let mut visible: Vec<_> = world.visible_chunks(&frustum).map(|chunk| [chunk.position.x, chunk.position.y, chunk.position.z]).collect();
+    visible.sort();
+    // the chunk ahead and the one containing the camera, not the one behind or far to the left
+    assert_eq!(visible, vec![[-1, -1, -1], [0, 0, -2]]);
+}
@@ -0,0 +1,228 @@
+use block_mesh::ndshape::ConstShape;
+use glm::{vec3, I32Vec3};
+
+use crate::game::block::{Block, BlockFace, BlockProperties, BlockRegistry, BlockTextures, Opacity};
+use crate::game::chunk::{pad_blocks, Blocks, Chunk, ChunkShape, MeshingMode, PaddedChunkShape};
+use crate::game::chunk_vertex::ChunkVertex;
+use crate::game::light::{LightLevel, PaddedLight, MAX_LIGHT};
+use crate::game::world::World;
+use crate::game::{CHUNK_SIZE, WORLD_HEIGHT};
+
+use super::{empty, full, stone};
+
+fn lamp() -> BlockProperties {
+    BlockProperties {
+        name: String::from("lamp"),
+        opacity: Opacity::Opaque,
+        solid: true,
+        textures: BlockTextures::all("stone"),
+        light_emission: 14,
+        hardness: 0.3,
+    }
+}
+
+/// The height of the lowest chunk lying entirely in open sky
+const SKY: i32 = WORLD_HEIGHT / CHUNK_SIZE;
+
+/// A world with a lamp block registered and `chunks` inserted in order, with their light fully spread
+fn world_with(chunks: &[(I32Vec3, Blocks)]) -> World {
+    let mut registry = BlockRegistry::default();
+    registry.register(lamp()).unwrap();
+    let mut world = World::default().set_registry(registry);
+    for (position, blocks) in chunks {
+        world.insert_chunk(Chunk::new(*position, *blocks));
+    }
+    assert!(world.propagate_light(usize::MAX));
+    world
+}
+
+fn block(world: &World, name: &str) -> Block {
+    world.registry().block(name).unwrap()
+}
+
+fn sky(world: &World, position: I32Vec3) -> u8 {
+    world.get_light(&position).unwrap().sky()
+}
+
+fn block_light(world: &World, position: I32Vec3) -> u8 {
+    world.get_light(&position).unwrap().block()
+}
+
+#[test]
+fn light_levels_pack_both_channels() {
+    let light = LightLevel::new(12, 3);
+    assert_eq!((light.sky(), light.block()), (12, 3));
+    assert_eq!(LightLevel::new(40, 20), LightLevel::new(MAX_LIGHT, MAX_LIGHT));
+    assert_eq!(LightLevel::SKY.sky(), MAX_LIGHT);
+    assert_eq!(LightLevel::SKY.block(), 0);
+}
+
+#[test]
+fn vertex_light_round_trips_without_touching_other_fields() {
+    let vertex = ChunkVertex::new([16, 3, 9], BlockFace::South, [4, 16], 300).set_light(LightLevel::new(9, 14));
+    assert_eq!(vertex.light(), LightLevel::new(9, 14));
+    assert_eq!(vertex.position(), [16, 3, 9]);
+    assert_eq!(vertex.face(), BlockFace::South);
+    assert_eq!(vertex.uv(), [4, 16]);
+    assert_eq!(vertex.layer(), 300);
+    assert_eq!(vertex.set_light(LightLevel::DARK).light(), LightLevel::DARK);
+}
+
+#[test]
+fn everything_above_the_world_height_is_open_sky() {
+    let world = world_with(&[(vec3(0, SKY, 0), empty())]);
+    for position in [vec3(0, WORLD_HEIGHT, 0), vec3(8, WORLD_HEIGHT + 15, 8), vec3(15, WORLD_HEIGHT, 3)] {
+        assert_eq!(world.get_light(&position), Some(LightLevel::SKY));
+    }
+}
+
+#[test]
+fn sky_light_only_reaches_below_the_world_height_through_loaded_chunks() {
+    // nothing above is loaded, which says nothing about whether the sky can be seen from here
+    let mut world = world_with(&[(vec3(0, SKY - 2, 0), empty())]);
+    assert_eq!(sky(&world, vec3(8, WORLD_HEIGHT - 17, 8)), 0);
+
+    world.insert_chunk(Chunk::new(vec3(0, SKY, 0), empty()));
+    world.insert_chunk(Chunk::new(vec3(0, SKY - 1, 0), empty()));
+    assert!(world.propagate_light(usize::MAX));
+    assert_eq!(sky(&world, vec3(8, WORLD_HEIGHT - 32, 8)), MAX_LIGHT);
+}
+
+#[test]
+fn a_roof_shades_the_chunks_below() {
+    let world = world_with(&[(vec3(0, SKY, 0), empty()), (vec3(0, SKY - 1, 0), full()), (vec3(0, SKY - 2, 0), empty())]);
+    for position in [vec3(0, WORLD_HEIGHT - 32, 0), vec3(8, WORLD_HEIGHT - 17, 8), vec3(15, WORLD_HEIGHT - 25, 3)] {
+        assert_eq!(sky(&world, position), 0);
+    }
+}
+
+#[test]
+fn sky_light_falls_undimmed_down_a_shaft() {
+    let mut roof = full();
+    for y in 0..16 {
+        roof[ChunkShape::linearize([8, y, 8]) as usize] = Block::AIR;
+    }
+    let world = world_with(&[(vec3(0, SKY, 0), empty()), (vec3(0, SKY - 1, 0), roof), (vec3(0, SKY - 2, 0), empty())]);
+    let floor = WORLD_HEIGHT - 32;
+    assert_eq!(sky(&world, vec3(8, WORLD_HEIGHT - 1, 8)), MAX_LIGHT);
+    assert_eq!(sky(&world, vec3(8, floor, 8)), MAX_LIGHT);
+    // dimming by one per block once it spreads sideways
+    assert_eq!(sky(&world, vec3(9, floor, 8)), 14);
+    assert_eq!(sky(&world, vec3(11, floor, 9)), 11);
+    assert_eq!(sky(&world, vec3(0, floor + 15, 0)), 0);
+}
+
+#[test]
+fn placing_a_block_shades_the_column_below() {
+    let mut world = world_with(&[(vec3(0, SKY, 0), empty()), (vec3(0, SKY - 1, 0), empty())]);
+    let top = WORLD_HEIGHT - 1;
+    world.set_block(&vec3(8, top, 8), stone());
+    assert_eq!(sky(&world, vec3(8, top, 8)), 0);
+    assert_eq!(sky(&world, vec3(8, top - 15, 8)), 14);
+    assert_eq!(sky(&world, vec3(9, top - 15, 8)), MAX_LIGHT);
+
+    world.set_block(&vec3(8, top, 8), Block::AIR);
+    assert_eq!(sky(&world, vec3(8, top - 15, 8)), MAX_LIGHT);
+}
+
+#[test]
+fn unloading_a_chunk_removes_the_light_it_shone_into_its_neighbours() {
+    let mut world = world_with(&[(vec3(0, SKY, 0), empty()), (vec3(0, SKY - 1, 0), empty()), (vec3(0, SKY - 2, 0), empty())]);
+    let lamp = block(&world, "lamp");
+    world.insert_chunk(Chunk::new(vec3(1, SKY - 2, 0), empty()));
+    world.set_block(&vec3(17, WORLD_HEIGHT - 24, 8), lamp);
+    assert_eq!(block_light(&world, vec3(14, WORLD_HEIGHT - 24, 8)), 11);
+
+    world.remove_chunk(&vec3(0, SKY - 1, 0));
+    world.remove_chunk(&vec3(1, SKY - 2, 0));
+    assert!(world.propagate_light(usize::MAX));
+    assert_eq!(sky(&world, vec3(8, WORLD_HEIGHT - 17, 8)), 0);
+    assert_eq!(block_light(&world, vec3(14, WORLD_HEIGHT - 24, 8)), 0);
+}
+
+#[test]
+fn light_is_spread_a_budget_at_a_time() {
+    let mut world = world_with(&[(vec3(0, SKY, 0), empty())]);
+    world.insert_chunk(Chunk::new(vec3(0, SKY - 1, 0), empty()));
+    assert!(!world.propagate_light(100));
+    while !world.propagate_light(100) {}
+    assert_eq!(sky(&world, vec3(3, WORLD_HEIGHT - 16, 12)), MAX_LIGHT);
+}
+
+#[test]
+fn editing_blocks_leaves_streamed_light_to_the_budget() {
+    let mut world = world_with(&[(vec3(0, SKY, 0), empty())]);
+    world.insert_chunk(Chunk::new(vec3(0, SKY - 1, 0), empty()));
+    world.set_block(&vec3(8, WORLD_HEIGHT - 1, 8), stone());
+    assert_eq!(sky(&world, vec3(8, WORLD_HEIGHT - 1, 8)), 0);
+    // the chunk below is still waiting for its light
+    assert_eq!(sky(&world, vec3(3, WORLD_HEIGHT - 12, 12)), 0);
+    assert!(!world.propagate_light(0));
+    while !world.propagate_light(100) {}
+    assert_eq!(sky(&world, vec3(3, WORLD_HEIGHT - 12, 12)), MAX_LIGHT);
+}
+
+#[test]
+fn block_light_spreads_across_chunk_borders() {
+    let mut world = world_with(&[(vec3(0, 0, 0), empty()), (vec3(1, 0, 0), empty())]);
+    let lamp = block(&world, "lamp");
+    world.set_block(&vec3(12, 8, 8), lamp);
+    assert_eq!(block_light(&world, vec3(12, 8, 8)), 14);
+    assert_eq!(block_light(&world, vec3(16, 8, 8)), 10);
+    assert_eq!(block_light(&world, vec3(20, 9, 8)), 5);
+    // the lamp doesn't touch the neighbour, only its light does
+    assert!(world.is_dirty(&vec3(1, 0, 0)));
+}
+
+#[test]
+fn removing_a_light_source_darkens_what_it_lit() {
+    let mut world = world_with(&[(vec3(0, 0, 0), empty()), (vec3(1, 0, 0), empty())]);
+    let lamp = block(&world, "lamp");
+    world.set_block(&vec3(12, 8, 8), lamp);
+    world.set_block(&vec3(12, 8, 8), Block::AIR);
+    for position in [vec3(12, 8, 8), vec3(13, 8, 8), vec3(16, 8, 8), vec3(20, 9, 8)] {
+        assert_eq!(block_light(&world, position), 0);
+    }
+
+    world.set_block(&vec3(12, 8, 8), lamp);
+    assert_eq!(block_light(&world, vec3(16, 8, 8)), 10);
+}
+
+#[test]
+fn light_is_baked_from_the_voxel_in_front_of_each_face() {
+    let registry = BlockRegistry::default();
+    let mut blocks = empty();
+    blocks[ChunkShape::linearize([4, 4, 4]) as usize] = stone();
+    let mut light: PaddedLight = [LightLevel::SKY; PaddedChunkShape::SIZE as usize];
+    // padded coordinates are one higher, this is the voxel above the block
+    light[PaddedChunkShape::linearize([5, 6, 5]) as usize] = LightLevel::new(7, 3);
+
+    let mesh = Chunk::mesh_padded(&pad_blocks(&blocks, |_| None), &light, &registry, MeshingMode::Greedy);
+    assert_eq!(mesh.vertices.len(), 24);
+    for vertex in &mesh.vertices {
+        let expected = if vertex.face() == BlockFace::Top { LightLevel::new(7, 3) } else { LightLevel::SKY };
+        assert_eq!(vertex.light(), expected, "{:?} face", vertex.face());
+    }
+}
+
+#[test]
+fn greedy_meshing_does_not_merge_differently_lit_faces() {
+    let registry = BlockRegistry::default();
+    let mut blocks = empty();
+    for x in 0..16 {
+        for z in 0..16 {
+            blocks[ChunkShape::linearize([x, 0, z]) as usize] = stone();
+        }
+    }
+    let padded = pad_blocks(&blocks, |_| None);
+    let top_quads = |light: &PaddedLight| {
+        let mesh = Chunk::mesh_padded(&padded, light, &registry, MeshingMode::Greedy);
+        mesh.vertices.iter().filter(|vertex| vertex.face() == BlockFace::Top).count() / 4
+    };
+
+    let mut light: PaddedLight = [LightLevel::SKY; PaddedChunkShape::SIZE as usize];
+    assert_eq!(top_quads(&light), 1);
+
+    light[PaddedChunkShape::linearize([8, 2, 8]) as usize] = LightLevel::new(10, 0);
+    assert!(top_quads(&light) > 1);
+}
@@ -0,0 +1,49 @@
+use glm::vec3;
+
+use crate::engine::mesh::{MeshData, MeshDataError, TextureRef, Vertex};
+use crate::game::block::BlockRegistry;
+use crate::game::chunk::{Chunk, MeshingMode};
+
+use super::empty;
+
+fn quad() -> MeshData {
+    let vertices = [
+        vec3(0.0, 0.0, 0.0),
+        vec3(1.0, 0.0, 0.0),
+        vec3(1.0, 1.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+    ].into_iter().map(|position| Vertex {
+        position,
+        normal: vec3(0.0, 0.0, 1.0),
+        texCoords: vec3(position.x, position.y, 0.0),
+        ..Default::default()
+    }).collect();
+
+    MeshData::new(
+        vertices,
+        vec![0, 1, 2, 0, 2, 3],
+        vec![TextureRef { type_: String::from("texture_diffuse"), path: String::from("dirt.png") }]
+    )
+}
+
+#[test]
+fn mesh_data_round_trips_through_bytes() {
+    let mesh = quad();
+    let restored = MeshData::from_bytes(&mesh.to_bytes()).unwrap();
+    assert_eq!(restored, mesh);
+    assert_eq!(restored.triangle_count(), 2);
+}
+
+#[test]
+fn truncated_mesh_data_is_rejected() {
+    let bytes = quad().to_bytes();
+    let result = MeshData::from_bytes(&bytes[..bytes.len() - 1]);
+    assert_eq!(result, Err(MeshDataError::UnexpectedEnd(bytes.len() - 1)));
+}
+
+#[test]
+fn chunk_meshing_does_not_need_gl() {
+    let blocks = empty();
+    let mesh = Chunk::mesh_blocks(&blocks, &BlockRegistry::default(), MeshingMode::Greedy);
+    assert!(mesh.is_empty());
+}
@@ -0,0 +1,42 @@
+use crate::engine::mesh::MeshData;
+use crate::game::block::{Block, BlockRegistry};
+use crate::game::chunk::{Blocks, ChunkShape};
+use crate::game::chunk_vertex::ChunkVertex;
+use block_mesh::ndshape::ConstShape;
+
+mod block_definitions;
+mod block_registry;
+mod chunk_meshing;
+mod mesh_data;
+mod texture_array;
+mod world_edits;
+mod shader_errors;
+mod shader_reload;
+mod shader_preprocessor;
+mod uniform_cache;
+mod uniform_buffer;
+mod vertex_layout;
+mod frustum;
+mod chunk_visibility;
+mod sun;
+mod lighting;
+mod ambient_occlusion;
+mod chunk_workers;
+
+// fixtures shared by the test modules, built from the default block registry
+
+type ChunkMesh = MeshData<ChunkVertex>;
+
+fn stone() -> Block {
+    BlockRegistry::default().block("stone").unwrap()
+}
+
+/// A chunk of nothing but air
+fn empty() -> Blocks {
+    [Block::AIR; ChunkShape::SIZE as usize]
+}
+
+/// A chunk of solid stone
+fn full() -> Blocks {
+    [stone(); ChunkShape::SIZE as usize]
+}
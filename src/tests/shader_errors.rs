@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use crate::engine::shader::{parse_info_log, ShaderDiagnostic, ShaderError, ShaderStage};
+
+fn diagnostic(line: Option<u32>, message: &str) -> ShaderDiagnostic {
+    ShaderDiagnostic { file: None, line, message: String::from(message) }
+}
+
+#[test]
+fn mesa_logs() {
+    let log = "0:12(5): error: `foo' undeclared\n0:14(1): error: syntax error, unexpected '}'\n";
+    assert_eq!(parse_info_log(log), vec![
+        diagnostic(Some(12), "error: `foo' undeclared"),
+        diagnostic(Some(14), "error: syntax error, unexpected '}'"),
+    ]);
+}
+
+#[test]
+fn nvidia_logs() {
+    let log = "0(7) : error C1008: undefined variable \"Colour\"\n";
+    assert_eq!(parse_info_log(log), vec![diagnostic(Some(7), "error C1008: undefined variable \"Colour\"")]);
+}
+
+#[test]
+fn amd_logs() {
+    let log = "ERROR: 0:3: 'vec5' : no matching overloaded function found\nERROR: 1 compilation errors.  No code generated.\n\0";
+    assert_eq!(parse_info_log(log), vec![
+        diagnostic(Some(3), "error: 'vec5' : no matching overloaded function found"),
+        diagnostic(None, "ERROR: 1 compilation errors.  No code generated."),
+    ]);
+}
+
+#[test]
+fn unrecognised_lines_are_kept_whole() {
+    let log = "error: vertex shader lacks `main'\n\n   ";
+    assert_eq!(parse_info_log(log), vec![diagnostic(None, "error: vertex shader lacks `main'")]);
+    assert!(parse_info_log("").is_empty());
+}
+
+#[test]
+fn errors_name_the_stage_file_and_lines() {
+    let err = ShaderError::Compile {
+        stage: ShaderStage::Fragment,
+        path: PathBuf::from("chunk.frag"),
+        diagnostics: parse_info_log("0:9(2): error: `TexCoords' undeclared"),
+    };
+    assert_eq!(err.to_string(), "Failed to compile fragment shader chunk.frag:\n    line 9: error: `TexCoords' undeclared");
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::engine::preprocessor::{preprocess, preprocess_file, PreprocessError, PreprocessedSource};
+use crate::engine::shader::{parse_info_log, trace_diagnostics};
+
+const NO_DEFINES: &[(&str, &str)] = &[];
+
+/// Preprocesses `root` from an in-memory set of files
+fn run(files: &[(&str, &str)], root: &str, defines: &[(&str, &str)]) -> Result<PreprocessedSource, PreprocessError> {
+    let files: HashMap<PathBuf, &str> = files.iter().map(|(path, source)| (PathBuf::from(path), *source)).collect();
+    preprocess(Path::new(root), defines, |path| {
+        files.get(path).map(|source| String::from(*source)).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    })
+}
+
+#[test]
+fn sources_without_directives_are_unchanged() {
+    let source = "#version 330 core\nvoid main() {}\n";
+    let result = run(&[("a.frag", source)], "a.frag", NO_DEFINES).unwrap();
+    assert_eq!(result.code, source);
+    assert_eq!(result.locate(2), Some((Path::new("a.frag"), 2)));
+}
+
+#[test]
+fn includes_resolve_relative_to_the_including_file() {
+    let result = run(&[
+        ("shaders/chunk.frag", "#version 330 core\n#include \"lib/fog.glsl\"\nvoid main() {}"),
+        ("shaders/lib/fog.glsl", "#include \"../common.glsl\"\nfloat fog() { return 1.0; }"),
+        ("shaders/common.glsl", "#version 330 core\nconst float PI = 3.14;"),
+    ], "shaders/chunk.frag", NO_DEFINES).unwrap();
+
+    // included #version lines are dropped
+    assert_eq!(result.code, "#version 330 core\nconst float PI = 3.14;\nfloat fog() { return 1.0; }\nvoid main() {}\n");
+    assert_eq!(result.files, [
+        PathBuf::from("shaders/chunk.frag"),
+        PathBuf::from("shaders/lib/fog.glsl"),
+        PathBuf::from("shaders/common.glsl"),
+    ]);
+    assert_eq!(result.locate(2), Some((Path::new("shaders/common.glsl"), 2)));
+    assert_eq!(result.locate(3), Some((Path::new("shaders/lib/fog.glsl"), 2)));
+    assert_eq!(result.locate(4), Some((Path::new("shaders/chunk.frag"), 3)));
+    assert_eq!(result.locate(5), None);
+}
+
+#[test]
+fn defines_follow_the_version() {
+    let result = run(&[("a.vert", "// comment\n#version 330 core\nvoid main() {}")], "a.vert", &[("FOG", "1"), ("MAX_LIGHTS", "8")]).unwrap();
+    assert_eq!(result.code, "#version 330 core\n#define FOG 1\n#define MAX_LIGHTS 8\n// comment\nvoid main() {}\n");
+    assert_eq!(result.locate(1), Some((Path::new("a.vert"), 2)));
+    assert_eq!(result.locate(2), None);
+    assert_eq!(result.locate(4), Some((Path::new("a.vert"), 1)));
+}
+
+#[test]
+fn invalid_define_names_are_rejected() {
+    let result = run(&[("a.vert", "")], "a.vert", &[("2FAST", "1")]);
+    assert!(matches!(result, Err(PreprocessError::InvalidDefine(name)) if name == "2FAST"));
+}
+
+#[test]
+fn include_cycles_are_reported() {
+    let result = run(&[
+        ("a.glsl", "#include \"b.glsl\""),
+        ("b.glsl", "#include \"./a.glsl\""),
+    ], "a.glsl", NO_DEFINES);
+    let Err(err @ PreprocessError::IncludeCycle { .. }) = result else {
+        panic!("expected an include cycle, got {:?}", result);
+    };
+    assert_eq!(err.to_string(), "Include cycle: a.glsl -> b.glsl -> a.glsl");
+}
+
+#[test]
+fn a_file_may_be_included_twice_without_a_cycle() {
+    let result = run(&[
+        ("a.glsl", "#include \"c.glsl\"\n#include \"c.glsl\""),
+        ("c.glsl", "float c;"),
+    ], "a.glsl", NO_DEFINES).unwrap();
+    assert_eq!(result.code, "float c;\nfloat c;\n");
+    assert_eq!(result.files.len(), 2);
+}
+
+#[test]
+fn bad_includes_name_the_file_and_line() {
+    let result = run(&[("a.glsl", "\n#include <b.glsl>")], "a.glsl", NO_DEFINES);
+    assert!(matches!(result, Err(PreprocessError::MalformedInclude { line: 2, .. })));
+
+    let result = run(&[("a.glsl", "#include \"missing.glsl\"")], "a.glsl", NO_DEFINES);
+    assert!(matches!(result, Err(PreprocessError::Io { path, .. }) if path == Path::new("missing.glsl")));
+}
+
+#[test]
+fn files_read_are_recorded_even_when_preprocessing_fails() {
+    let dir = std::env::temp_dir().join(format!("shader_preprocessor_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (root, fog, missing) = (dir.join("a.frag"), dir.join("fog.glsl"), dir.join("missing.glsl"));
+    std::fs::write(&root, "#include \"fog.glsl\"\n#include \"missing.glsl\"").unwrap();
+    std::fs::write(&fog, "float fog;").unwrap();
+
+    let mut read = Vec::new();
+    let result = preprocess_file(&root, NO_DEFINES, &mut read);
+    assert!(matches!(result, Err(PreprocessError::Io { .. })));
+    // the missing file is read too, so creating it can be noticed
+    assert_eq!(read, [root, fog, missing]);
+}
+
+#[test]
+fn driver_errors_are_traced_back_to_the_original_file() {
+    let result = run(&[
+        ("a.frag", "#version 330 core\n#include \"fog.glsl\"\nvoid main() {}"),
+        ("fog.glsl", "float fog() {\n    return foo;\n}"),
+    ], "a.frag", &[("FOG", "1")]).unwrap();
+    // version, define, then fog.glsl starting on line 3
+    let diagnostics = trace_diagnostics(parse_info_log("0:4(12): error: `foo' undeclared"), &result);
+    assert_eq!(diagnostics[0].to_string(), "fog.glsl:2: error: `foo' undeclared");
+}
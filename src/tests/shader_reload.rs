@@ -0,0 +1,56 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::engine::shader::ShaderSources;
+
+/// A fresh pair of shader files in a directory unique to the calling test
+fn shader_files(test: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("shader_reload_{}_{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let paths = (dir.join("test.vert"), dir.join("test.frag"));
+    fs::write(&paths.0, "void main() {}").unwrap();
+    fs::write(&paths.1, "void main() {}").unwrap();
+    paths
+}
+
+/// Rewrites `path` and pushes its modification time forward, filesystem timestamps can be too coarse to see a quick edit
+fn touch(path: &PathBuf, seconds: u64) {
+    fs::write(path, "void main() { }").unwrap();
+    File::options().write(true).open(path).unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(seconds)).unwrap();
+}
+
+#[test]
+fn unchanged_files_are_not_reported() {
+    let (vertex, fragment) = shader_files("unchanged");
+    let mut sources = ShaderSources::new(&vertex, &fragment);
+    assert!(!sources.poll());
+    assert!(!sources.poll());
+}
+
+#[test]
+fn each_change_is_reported_once() {
+    let (vertex, fragment) = shader_files("changed");
+    let mut sources = ShaderSources::new(&vertex, &fragment);
+
+    touch(&fragment, 10);
+    assert!(sources.poll());
+    assert!(!sources.poll());
+
+    touch(&vertex, 20);
+    assert!(sources.poll());
+    assert!(!sources.poll());
+}
+
+#[test]
+fn missing_files_count_as_unchanged() {
+    let (vertex, fragment) = shader_files("missing");
+    let mut sources = ShaderSources::new(&vertex, &fragment);
+    fs::remove_file(&fragment).unwrap();
+    assert!(!sources.poll());
+
+    // reappearing, e.g. once an editor finishes saving, is a change
+    touch(&fragment, 10);
+    assert!(sources.poll());
+}
@@ -0,0 +1,38 @@
+use cgmath::{InnerSpace, Vector3};
+
+use crate::game::block::BlockFace;
+use crate::game::sun::Sun;
+
+fn light(sun: &Sun, face: BlockFace) -> Vector3<f32> {
+    let [x, y, z] = face.normal();
+    sun.light(Vector3::new(x as f32, y as f32, z as f32))
+}
+
+#[test]
+fn direction_is_normalised() {
+    let sun = Sun::default().set_direction(Vector3::new(0.0, -3.0, 4.0));
+    assert!((sun.direction.magnitude() - 1.0).abs() < 1e-6);
+    assert!((sun.direction.z - 0.8).abs() < 1e-6);
+}
+
+#[test]
+fn faces_towards_the_sun_are_brightest() {
+    let sun = Sun::default()
+        .set_direction(Vector3::new(0.0, -1.0, 0.0))
+        .set_colour(Vector3::new(1.0, 1.0, 1.0))
+        .set_ambient(Vector3::new(0.25, 0.25, 0.25));
+    assert_eq!(light(&sun, BlockFace::Top), Vector3::new(1.25, 1.25, 1.25));
+    // perpendicular and facing away both get ambient only
+    assert_eq!(light(&sun, BlockFace::East), sun.ambient);
+    assert_eq!(light(&sun, BlockFace::Bottom), sun.ambient);
+}
+
+#[test]
+fn default_sun_shades_every_face_differently_enough_to_read_shapes() {
+    let sun = Sun::default();
+    let [top, west, north, east, south, bottom] = [BlockFace::Top, BlockFace::West, BlockFace::North, BlockFace::East, BlockFace::South, BlockFace::Bottom]
+        .map(|face| light(&sun, face).x);
+    assert!(top > east && top > south);
+    assert!(east > west && south > north);
+    assert!(west == bottom && north == bottom);
+}
@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use block_mesh::ndshape::ConstShape;
+
+use crate::engine::texture_array::{TextureArrayError, TextureLayers};
+use crate::game::block::{texture_path, BlockFace, BlockRegistry};
+use crate::game::chunk::{Chunk, ChunkShape, MeshingMode};
+
+use super::empty;
+
+fn asset(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(name)
+}
+
+#[test]
+fn each_texture_gets_one_layer() {
+    let registry = BlockRegistry::default();
+    assert_eq!(registry.texture_names(), ["stone", "dirt", "grass_side", "grass_top"]);
+
+    let grass = registry.block("grass").unwrap();
+    let dirt = registry.block("dirt").unwrap();
+    // the grass bottom shares the dirt layer
+    assert_eq!(registry.texture_layer(grass, BlockFace::Bottom), registry.texture_layer(dirt, BlockFace::Top));
+    assert_eq!(registry.texture_layer(grass, BlockFace::Top), 3);
+    assert_eq!(registry.texture_layer(grass, BlockFace::West), 2);
+}
+
+#[test]
+fn meshes_sample_the_layer_of_each_face() {
+    let registry = BlockRegistry::default();
+    let grass = registry.block("grass").unwrap();
+    let mut blocks = empty();
+    blocks[ChunkShape::linearize([3, 3, 3]) as usize] = grass;
+
+    let mesh = Chunk::mesh_blocks(&blocks, &registry, MeshingMode::Greedy);
+    assert!(mesh.textures.is_empty());
+    for vertex in &mesh.vertices {
+        assert_eq!(vertex.layer(), registry.texture_layer(grass, vertex.face()));
+    }
+}
+
+#[test]
+fn shipped_block_textures_load_into_layers() {
+    let registry = BlockRegistry::default();
+    let layers = TextureLayers::load(registry.texture_names().iter().map(|name| texture_path(&asset(""), name))).unwrap();
+    assert_eq!((layers.width, layers.height, layers.layers), (16, 16, 4));
+    assert_eq!(layers.data.len(), 16 * 16 * 4 * 4);
+}
+
+#[test]
+fn layers_must_share_a_size() {
+    let result = TextureLayers::load([asset("dirt.png"), asset("images.png")]);
+    assert!(matches!(result, Err(TextureArrayError::SizeMismatch { expected: (16, 16), .. })));
+}
+
+#[test]
+fn missing_images_and_empty_arrays_are_errors() {
+    assert!(matches!(TextureLayers::load([asset("missing.png")]), Err(TextureArrayError::Image { .. })));
+    assert!(matches!(TextureLayers::load(Vec::<PathBuf>::new()), Err(TextureArrayError::Empty)));
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+use cgmath::{Matrix4, Point3, Vector3};
+
+use crate::engine::uniform_buffer::{FrameData, Std140Writer};
+
+fn floats(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks(4).map(|chunk| f32::from_ne_bytes(chunk.try_into().unwrap())).collect()
+}
+
+#[test]
+fn scalars_pack_after_a_vec3_but_vectors_align_to_16_bytes() {
+    let bytes = Std140Writer::default()
+        .float(1.0)
+        .vec3([2.0, 3.0, 4.0])
+        .float(5.0)
+        .float(6.0)
+        .vec3([7.0; 3])
+        .finish();
+    assert_eq!(floats(&bytes), vec![1.0, 0.0, 0.0, 0.0, 2.0, 3.0, 4.0, 5.0, 6.0, 0.0, 0.0, 0.0, 7.0, 7.0, 7.0, 0.0]);
+}
+
+#[test]
+fn finish_pads_to_a_multiple_of_16_bytes() {
+    assert_eq!(Std140Writer::default().float(1.0).finish().len(), 16);
+    assert_eq!(Std140Writer::default().mat4(&[0.0; 16]).finish().len(), 64);
+}
+
+#[test]
+fn frame_data_matches_the_glsl_block_offsets() {
+    let data = FrameData {
+        view: Matrix4::from_scale(2.0),
+        projection: Matrix4::from_scale(3.0),
+        camera_position: Point3::new(1.0, 2.0, 3.0),
+        time: 4.0,
+        fog_colour: Vector3::new(0.5, 0.25, 0.125),
+        fog_start: 48.0,
+        fog_end: 64.0,
+        sun_direction: Vector3::new(0.0, -1.0, 0.0),
+        sun_colour: Vector3::new(0.9, 0.8, 0.7),
+        ambient: Vector3::new(0.1, 0.2, 0.3),
+    };
+    let values = floats(&data.to_std140());
+    assert_eq!(values.len(), 224 / 4);
+    // view at 0, projection at 64, both column major
+    assert_eq!(values[0], 2.0);
+    assert_eq!(values[15], 1.0);
+    assert_eq!(values[16], 3.0);
+    // cameraPosition at 128 with time packed into its fourth component, then the fog
+    assert_eq!(values[32..41], [1.0, 2.0, 3.0, 4.0, 0.5, 0.25, 0.125, 48.0, 64.0]);
+    // fogEnd leaves a gap before the sun's vec3s, each starting on a 16 byte boundary
+    assert_eq!(values[44..47], [0.0, -1.0, 0.0]);
+    assert_eq!(values[48..51], [0.9, 0.8, 0.7]);
+    assert_eq!(values[52..55], [0.1, 0.2, 0.3]);
+}
+
+#[test]
+fn shipped_block_declares_the_fields_in_the_same_order() {
+    let source = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("frame_data.glsl")).unwrap();
+    let block = source.split_once('{').unwrap().1.split_once('}').unwrap().0;
+    let members: Vec<&str> = block.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    assert_eq!(members, [
+        "mat4 view;",
+        "mat4 projection;",
+        "vec3 cameraPosition;",
+        "float time;",
+        "vec3 fogColour;",
+        "float fogStart;",
+        "float fogEnd;",
+        "vec3 sunDirection;",
+        "vec3 sunColour;",
+        "vec3 ambient;",
+    ]);
+}
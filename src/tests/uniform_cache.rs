@@ -0,0 +1,58 @@
+use cgmath::{Matrix4, Vector3};
+
+use crate::engine::uniform::{Uniform, UniformCache, UniformError, UniformInfo};
+
+fn cache() -> UniformCache {
+    let mut cache = UniformCache::default();
+    cache.insert("model", UniformInfo { location: 0, gl_type: gl::FLOAT_MAT4, size: 1 });
+    cache.insert("sunDirection", UniformInfo { location: 1, gl_type: gl::FLOAT_VEC3, size: 1 });
+    cache.insert("blockTextures", UniformInfo { location: 2, gl_type: gl::SAMPLER_2D_ARRAY, size: 1 });
+    cache.insert("lights[0]", UniformInfo { location: 3, gl_type: gl::FLOAT_VEC4, size: 8 });
+    cache
+}
+
+#[test]
+fn matching_types_give_the_location() {
+    let cache = cache();
+    assert_eq!(cache.location::<Matrix4<f32>>("model"), Ok(0));
+    assert_eq!(cache.location::<glm::Mat4>("model"), Ok(0));
+    assert_eq!(cache.location::<&glm::Mat4>("model"), Ok(0));
+    assert_eq!(cache.location::<Vector3<f32>>("sunDirection"), Ok(1));
+    assert_eq!(cache.location::<glm::Vec3>("sunDirection"), Ok(1));
+}
+
+#[test]
+fn samplers_are_set_with_integers() {
+    let cache = cache();
+    assert_eq!(cache.location::<i32>("blockTextures"), Ok(2));
+    assert!(cache.location::<f32>("blockTextures").is_err());
+}
+
+#[test]
+fn arrays_can_be_named_without_an_index() {
+    let cache = cache();
+    assert_eq!(cache.location::<glm::Vec4>("lights"), Ok(3));
+    assert_eq!(cache.location::<glm::Vec4>("lights[0]"), Ok(3));
+}
+
+#[test]
+fn unknown_names_are_reported() {
+    let err = cache().location::<f32>("fogDensity").unwrap_err();
+    assert_eq!(err, UniformError::Missing(String::from("fogDensity")));
+    assert!(err.to_string().contains("fogDensity"));
+}
+
+#[test]
+fn wrong_types_are_reported_with_both_types() {
+    let err = cache().location::<glm::Vec4>("sunDirection").unwrap_err();
+    assert!(matches!(err, UniformError::WrongType { gl_type: gl::FLOAT_VEC3, .. }));
+    let message = err.to_string();
+    assert!(message.contains("vec3") && message.contains("Matrix<f32"), "{}", message);
+}
+
+#[test]
+fn scalars_only_accept_their_own_type() {
+    assert!(f32::accepts(gl::FLOAT) && !f32::accepts(gl::INT));
+    assert!(bool::accepts(gl::BOOL) && !bool::accepts(gl::FLOAT));
+    assert!(u32::accepts(gl::UNSIGNED_INT) && !u32::accepts(gl::INT));
+}
@@ -0,0 +1,93 @@
+use std::mem::size_of;
+
+use crate::engine::mesh::Vertex;
+use crate::game::chunk_vertex::ChunkVertex;
+use crate::engine::vertex_layout::{validate_layout, AttributeInfo, AttributeKind, LayoutMismatch, VertexAttribute, VertexLayout};
+use crate::vertex_layout;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct LineVertex {
+    position: [f32; 3],
+    colour: [u8; 4],
+    flags: u32,
+}
+
+vertex_layout! {
+    LineVertex {
+        0 => position: [gl::FLOAT; 3],
+        1 => colour: [gl::UNSIGNED_BYTE; 4] as Normalized,
+        2 => flags: [gl::UNSIGNED_INT; 1] as Integer,
+    }
+}
+
+fn input(name: &str, location: i32, gl_type: gl::types::GLenum) -> AttributeInfo {
+    AttributeInfo { name: String::from(name), location, gl_type }
+}
+
+#[test]
+fn chunk_shader_inputs_match_the_vertex_layout() {
+    // chunk.vert reads the packed vertex as a single uvec2
+    let inputs = [input("aData", 0, gl::UNSIGNED_INT_VEC2)];
+    assert_eq!(validate_layout(&inputs, ChunkVertex::ATTRIBUTES), Ok(()));
+}
+
+#[test]
+fn reading_normals_as_texture_coordinates_is_reported() {
+    // triangle.vert expects its texture coordinates where the vertex layout puts normals
+    let inputs = [input("Position", 0, gl::FLOAT_VEC3), input("aTexCoord", 1, gl::FLOAT_VEC2)];
+    let err = validate_layout(&inputs, Vertex::ATTRIBUTES).unwrap_err();
+    assert_eq!(err.0, vec![LayoutMismatch::WrongType {
+        name: String::from("aTexCoord"),
+        location: 1,
+        shader_type: gl::FLOAT_VEC2,
+        attribute: "normal",
+        layout_type: gl::FLOAT_VEC3,
+    }]);
+    assert!(err.to_string().contains("\"aTexCoord\" at location 1 is a vec2 but the vertex layout provides \"normal\" there as a vec3"), "{}", err);
+}
+
+#[test]
+fn inputs_without_an_attribute_are_reported() {
+    let inputs = [input("Position", 0, gl::FLOAT_VEC3), input("aColour", 7, gl::FLOAT_VEC4)];
+    let err = validate_layout(&inputs, Vertex::ATTRIBUTES).unwrap_err();
+    assert_eq!(err.0, vec![LayoutMismatch::Missing { name: String::from("aColour"), location: 7 }]);
+}
+
+#[test]
+fn built_in_inputs_are_ignored() {
+    let inputs = [input("gl_VertexID", -1, gl::INT)];
+    assert_eq!(validate_layout(&inputs, &[]), Ok(()));
+}
+
+#[test]
+fn integer_attributes_map_to_integer_inputs() {
+    let packed = VertexAttribute { name: "packed", location: 0, components: 2, component_type: gl::UNSIGNED_INT, kind: AttributeKind::Integer, offset: 0 };
+    assert_eq!(packed.shader_type(), gl::UNSIGNED_INT_VEC2);
+    assert_eq!(validate_layout(&[input("data", 0, gl::UNSIGNED_INT_VEC2)], &[packed]), Ok(()));
+    assert!(validate_layout(&[input("data", 0, gl::FLOAT_VEC2)], &[packed]).is_err());
+}
+
+#[test]
+fn integer_components_read_as_floats_map_to_float_inputs() {
+    let attribute = VertexAttribute { name: "colour", location: 0, components: 4, component_type: gl::UNSIGNED_BYTE, kind: AttributeKind::Normalized, offset: 0 };
+    assert_eq!(attribute.shader_type(), gl::FLOAT_VEC4);
+}
+
+#[test]
+fn vertex_layout_offsets_match_the_struct() {
+    let offsets: Vec<usize> = Vertex::ATTRIBUTES.iter().map(|attribute| attribute.offset).collect();
+    assert_eq!(offsets, vec![0, 12, 24, 36, 48]);
+    assert_eq!(size_of::<Vertex>(), 60);
+}
+
+#[test]
+fn vertex_layout_macro_describes_each_field() {
+    assert_eq!(LineVertex::ATTRIBUTES, &[
+        VertexAttribute { name: "position", location: 0, components: 3, component_type: gl::FLOAT, kind: AttributeKind::Float, offset: 0 },
+        VertexAttribute { name: "colour", location: 1, components: 4, component_type: gl::UNSIGNED_BYTE, kind: AttributeKind::Normalized, offset: 12 },
+        VertexAttribute { name: "flags", location: 2, components: 1, component_type: gl::UNSIGNED_INT, kind: AttributeKind::Integer, offset: 16 },
+    ]);
+    let inputs = [input("aPos", 0, gl::FLOAT_VEC3), input("aColour", 1, gl::FLOAT_VEC4), input("aFlags", 2, gl::UNSIGNED_INT)];
+    assert_eq!(validate_layout(&inputs, LineVertex::ATTRIBUTES), Ok(()));
+}
@@ -0,0 +1,111 @@
+use block_mesh::ndshape::ConstShape;
+use glm::{vec3, I32Vec3};
+
+use crate::game::block::Block;
+use crate::game::chunk::{Chunk, ChunkShape};
+use crate::game::world::World;
+
+use super::{empty, stone};
+
+/// A world holding empty chunks at `positions`, without generating or meshing anything
+fn world_with_chunks(positions: &[I32Vec3]) -> World {
+    let mut world = World::default();
+    for position in positions {
+        world.insert_chunk(Chunk::new(*position, empty()));
+    }
+    world
+}
+
+#[test]
+fn editing_unloaded_chunks_does_nothing() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0)]);
+    let stone = stone();
+    assert_eq!(world.set_block(&vec3(40, 0, 0), stone), None);
+    assert_eq!(world.dirty_chunk_count(), 0);
+}
+
+#[test]
+fn edits_inside_a_chunk_only_dirty_that_chunk() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0), vec3(1, 0, 0), vec3(-1, 0, 0)]);
+    let stone = stone();
+    assert_eq!(world.set_block(&vec3(8, 8, 8), stone), Some(Block::AIR));
+    assert_eq!(world.get_block(&vec3(8, 8, 8)), Some(stone));
+    assert!(world.is_dirty(&vec3(0, 0, 0)));
+    assert_eq!(world.dirty_chunk_count(), 1);
+}
+
+#[test]
+fn edits_on_a_border_dirty_the_touching_neighbours() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0), vec3(-1, 0, 0), vec3(0, 1, 0), vec3(1, 0, 0)]);
+    let stone = stone();
+    // the -x, +y and -z sides, the -z neighbour isn't loaded
+    world.set_block(&vec3(0, 15, 0), stone);
+    assert!(world.is_dirty(&vec3(0, 0, 0)));
+    assert!(world.is_dirty(&vec3(-1, 0, 0)));
+    assert!(world.is_dirty(&vec3(0, 1, 0)));
+    assert!(!world.is_dirty(&vec3(1, 0, 0)));
+    assert_eq!(world.dirty_chunk_count(), 3);
+}
+
+#[test]
+fn negative_positions_edit_the_right_chunk() {
+    let mut world = world_with_chunks(&[vec3(-1, -1, -1), vec3(0, -1, -1)]);
+    let stone = stone();
+    world.set_block(&vec3(-1, -16, -1), stone);
+    let chunk = world.get_chunk(&vec3(-1, -1, -1)).unwrap();
+    assert_eq!(chunk.blocks[ChunkShape::linearize([15, 0, 15]) as usize], stone);
+    // local x of 15 touches the +x neighbour
+    assert!(world.is_dirty(&vec3(0, -1, -1)));
+}
+
+#[test]
+fn dirty_chunks_are_meshed_once_with_the_edit() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0), vec3(1, 0, 0)]);
+    let stone = stone();
+    world.set_block(&vec3(15, 3, 3), stone);
+    world.set_block(&vec3(16, 3, 3), stone);
+
+    let mut meshes = world.mesh_dirty_chunks();
+    meshes.sort_by_key(|meshed| meshed.position.x);
+    assert_eq!(meshes.len(), 2);
+    // the faces the two blocks share across the chunk border are culled on both sides
+    assert_eq!(meshes[0].mesh.vertices.len() / 4, 5);
+    assert_eq!(meshes[1].mesh.vertices.len() / 4, 5);
+    assert_eq!(world.dirty_chunk_count(), 0);
+    assert!(world.mesh_dirty_chunks().is_empty());
+}
+
+#[test]
+fn replacing_a_block_with_itself_is_not_an_edit() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0)]);
+    assert_eq!(world.set_block(&vec3(1, 1, 1), Block::AIR), Some(Block::AIR));
+    assert_eq!(world.dirty_chunk_count(), 0);
+}
+
+#[test]
+fn edits_on_a_corner_dirty_the_diagonal_neighbours() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0), vec3(1, 1, 0), vec3(1, 1, 1), vec3(1, 0, 1), vec3(-1, 1, 1)]);
+    let stone = stone();
+    // the +x, +y and +z corner, whose edge and corner neighbours shade their faces against it
+    world.set_block(&vec3(15, 15, 15), stone);
+    assert!(world.is_dirty(&vec3(1, 1, 0)));
+    assert!(world.is_dirty(&vec3(1, 1, 1)));
+    assert!(world.is_dirty(&vec3(1, 0, 1)));
+    assert!(!world.is_dirty(&vec3(-1, 1, 1)));
+}
+
+#[test]
+fn chunks_can_be_changed_in_place_and_iterated() {
+    let mut world = world_with_chunks(&[vec3(0, 0, 0), vec3(-3, 2, -1)]);
+    world.get_chunk_mut(&vec3(-3, 2, -1)).unwrap().blocks[0] = stone();
+    assert_eq!(world.get_block(&vec3(-48, 32, -16)), Some(stone()));
+
+    for chunk in world.chunks_mut() {
+        chunk.blocks[1] = stone();
+    }
+    let mut positions: Vec<[i32; 3]> = world.chunks().filter(|chunk| chunk.blocks[1] == stone()).map(|chunk| chunk.position.into()).collect();
+    positions.sort();
+    assert_eq!(positions, vec![[-3, 2, -1], [0, 0, 0]]);
+    assert!(world.contains_chunk(&vec3(-3, 2, -1)));
+    assert!(!world.contains_chunk(&vec3(3, 2, -1)));
+}